@@ -0,0 +1,544 @@
+use near_sdk::{
+    env, log, near, require,
+    store::{LookupMap, Vector},
+    AccountId, BorshStorageKey, NearToken, PanicOnDefault, Promise,
+};
+use tee_rng_consumer::{
+    impl_randomness_callback, request_randomness, RandomBytes, RandomnessConsumer,
+    MIN_REQUEST_DEPOSIT, REQUEST_EXPIRATION_BLOCKS,
+};
+
+// Maximum number of entries per round, so the draw callback can read and clear all of them
+const MAX_ENTRIES: u32 = 100;
+
+#[near]
+#[derive(BorshStorageKey)]
+pub enum Prefix {
+    Entries,
+    Winners,
+    Refunds,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RoundStatus {
+    /// Accepting entries
+    Open,
+    /// Waiting for the random number
+    Drawing,
+    /// The round wasn't drawn and its entries are being refunded with `refund_entries`
+    Refunding,
+}
+
+/// Outcome of a drawn round, kept so anyone can verify the winner was selected from the random
+/// number returned by the RNG contract
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct Winner {
+    pub winner_id: AccountId,
+    pub random_number: Vec<u8>,
+    pub entries: u32,
+    pub prize: NearToken,
+}
+
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
+pub struct Contract {
+    rng_account_id: AccountId,
+    ticket_price: NearToken,
+    round_id: u64,
+    status: RoundStatus,
+    round_duration_blocks: u64,
+    round_start_height: u64,
+    draw_block_height: u64,
+    entries: Vector<AccountId>,
+    winners: LookupMap<u64, Winner>,
+    refunds: LookupMap<AccountId, NearToken>,
+}
+
+#[near]
+impl Contract {
+    /// Rounds can be drawn `round_duration_blocks` after they open, or as soon as they're full
+    #[init]
+    pub fn new(
+        rng_account_id: AccountId,
+        ticket_price: NearToken,
+        round_duration_blocks: u64,
+    ) -> Self {
+        Self {
+            rng_account_id,
+            ticket_price,
+            round_id: 1,
+            status: RoundStatus::Open,
+            round_duration_blocks,
+            round_start_height: env::block_height(),
+            draw_block_height: 0,
+            entries: Vector::new(Prefix::Entries),
+            winners: LookupMap::new(Prefix::Winners),
+            refunds: LookupMap::new(Prefix::Refunds),
+        }
+    }
+
+    /// Enter the current round by attaching the ticket price
+    #[payable]
+    pub fn enter(&mut self) {
+        require!(self.status == RoundStatus::Open, "Round is not open");
+        require!(
+            env::attached_deposit() == self.ticket_price,
+            "Attached deposit must equal the ticket price"
+        );
+        require!(self.entries.len() < MAX_ENTRIES, "Round is full");
+
+        self.entries.push(env::predecessor_account_id());
+    }
+
+    /// Close the round once it has ended and request a random number to draw the winner. The
+    /// caller attaches the RNG deposit, so it's never paid from the ticket payments.
+    #[payable]
+    pub fn draw(&mut self) -> Promise {
+        require!(self.status == RoundStatus::Open, "Round is not open");
+        require!(!self.entries.is_empty(), "No entries");
+        require!(
+            env::attached_deposit() == MIN_REQUEST_DEPOSIT,
+            "Attached deposit must equal the RNG deposit"
+        );
+        require!(
+            self.entries.len() == MAX_ENTRIES || env::block_height() >= self.get_round_end_height(),
+            "Round can't be drawn before it ends"
+        );
+
+        self.status = RoundStatus::Drawing;
+        self.draw_block_height = env::block_height();
+        request_randomness(self.rng_account_id.clone(), self.round_id)
+    }
+
+    /// Refund the entries of a round whose draw never called back, e.g. the callback ran out of
+    /// gas. Anyone can call it once the RNG request would have expired.
+    pub fn reset_round(&mut self) {
+        require!(
+            self.status == RoundStatus::Drawing,
+            "Round is not being drawn"
+        );
+        require!(
+            env::block_height() > self.draw_block_height + REQUEST_EXPIRATION_BLOCKS,
+            "Round can't be reset before the RNG request expires"
+        );
+
+        log!("Round {} reset", self.round_id);
+        self.status = RoundStatus::Refunding;
+    }
+
+    /// Credit the ticket price of up to `limit` entries of a round which wasn't drawn to their
+    /// accounts, and open the next round once all entries are refunded. Anyone can call it.
+    pub fn refund_entries(&mut self, limit: u32) -> u32 {
+        require!(
+            self.status == RoundStatus::Refunding,
+            "Round is not being refunded"
+        );
+
+        let mut refunded = 0;
+        while refunded < limit {
+            let Some(account_id) = self.entries.pop() else {
+                break;
+            };
+            let refund = self
+                .get_refund(account_id.clone())
+                .saturating_add(self.ticket_price);
+            self.refunds.insert(account_id, refund);
+            refunded += 1;
+        }
+
+        if self.entries.is_empty() {
+            log!("Round {} refunded", self.round_id);
+            self.start_next_round();
+        }
+        refunded
+    }
+
+    /// Withdraw the ticket payments refunded to the caller
+    pub fn withdraw(&mut self) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let refund = self.refunds.remove(&account_id).expect("No refund");
+        Promise::new(account_id).transfer(refund)
+    }
+
+    pub fn get_round_id(&self) -> u64 {
+        self.round_id
+    }
+
+    pub fn get_status(&self) -> RoundStatus {
+        self.status
+    }
+
+    pub fn get_ticket_price(&self) -> NearToken {
+        self.ticket_price
+    }
+
+    /// Block height from which the current round can be drawn
+    pub fn get_round_end_height(&self) -> u64 {
+        self.round_start_height + self.round_duration_blocks
+    }
+
+    pub fn get_entries(&self, offset: u32, limit: u32) -> Vec<&AccountId> {
+        self.entries
+            .iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    pub fn get_winner(&self, round_id: u64) -> Option<&Winner> {
+        self.winners.get(&round_id)
+    }
+
+    pub fn get_refund(&self, account_id: AccountId) -> NearToken {
+        self.refunds
+            .get(&account_id)
+            .copied()
+            .unwrap_or(NearToken::from_yoctonear(0))
+    }
+}
+
+impl RandomnessConsumer for Contract {
+    fn on_randomness(&mut self, request_id: u64, random_bytes: Vec<u8>) {
+        self.assert_drawing(request_id);
+
+        let entries: Vec<AccountId> = self.entries.iter().cloned().collect();
+        let winner_id = RandomBytes::new(&random_bytes)
+            .pick(&entries)
+            .unwrap()
+            .clone();
+        let prize = self.ticket_price.saturating_mul(entries.len() as u128);
+        Promise::new(winner_id.clone()).transfer(prize);
+
+        log!("Round {} won by {}", request_id, winner_id);
+        self.winners.insert(
+            request_id,
+            Winner {
+                winner_id,
+                random_number: random_bytes,
+                entries: entries.len() as u32,
+                prize,
+            },
+        );
+        self.start_next_round();
+    }
+
+    /// Refund the entries if the random number wasn't delivered, e.g. the request timed out.
+    /// The refunds are credited with `refund_entries`, which would exceed the callback gas for a
+    /// full round.
+    fn on_randomness_failed(&mut self, request_id: u64) {
+        self.assert_drawing(request_id);
+
+        log!("Round {} not drawn", request_id);
+        self.status = RoundStatus::Refunding;
+    }
+}
+
+impl_randomness_callback!(Contract);
+
+impl Contract {
+    fn assert_drawing(&self, round_id: u64) {
+        require!(
+            self.status == RoundStatus::Drawing && self.round_id == round_id,
+            "Round is not being drawn"
+        );
+    }
+
+    fn start_next_round(&mut self) {
+        self.entries.clear();
+        self.round_id += 1;
+        self.status = RoundStatus::Open;
+        self.round_start_height = env::block_height();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseError, PromiseOrValue};
+    use tee_rng::{Payment, RequestOutcome, ResumeData};
+
+    const TICKET_PRICE: NearToken = NearToken::from_millinear(100);
+    const ROUND_BLOCKS: u64 = 100;
+
+    fn raffle_account_id() -> AccountId {
+        accounts(0)
+    }
+
+    fn rng_account_id() -> AccountId {
+        accounts(1)
+    }
+
+    fn set_context(predecessor_account_id: AccountId, attached_deposit: NearToken) {
+        let context = VMContextBuilder::new()
+            .current_account_id(raffle_account_id())
+            .predecessor_account_id(predecessor_account_id)
+            .attached_deposit(attached_deposit)
+            .account_balance(NearToken::from_near(10))
+            .build();
+        testing_env!(context);
+    }
+
+    fn set_context_at_height(
+        predecessor_account_id: AccountId,
+        attached_deposit: NearToken,
+        block_height: u64,
+    ) {
+        let context = VMContextBuilder::new()
+            .current_account_id(raffle_account_id())
+            .predecessor_account_id(predecessor_account_id)
+            .attached_deposit(attached_deposit)
+            .account_balance(NearToken::from_near(10))
+            .block_height(block_height)
+            .build();
+        testing_env!(context);
+    }
+
+    /// Draw the round once it has ended, attaching the RNG deposit
+    fn draw_round(contract: &mut Contract) {
+        set_context_at_height(accounts(2), MIN_REQUEST_DEPOSIT, ROUND_BLOCKS);
+        contract.draw();
+    }
+
+    /// Resume a request in the RNG contract and return the value the raffle's callback receives
+    fn rng_result(outcome: Result<RequestOutcome, PromiseError>) -> Option<String> {
+        // The arguments the request was yielded with
+        let args = (
+            1u64,
+            raffle_account_id(),
+            Payment::Near(MIN_REQUEST_DEPOSIT),
+        );
+        let context = VMContextBuilder::new()
+            .current_account_id(rng_account_id())
+            .predecessor_account_id(rng_account_id())
+            .input(near_sdk::serde_json::to_vec(&args).unwrap())
+            .build();
+        testing_env!(context);
+
+        let mut rng = tee_rng::Contract::new(rng_account_id());
+        let result = rng.on_received_response(outcome.map(ResumeData::from));
+        match result {
+            PromiseOrValue::Value(value) => value,
+            PromiseOrValue::Promise(_) => panic!("Expected a value"),
+        }
+    }
+
+    fn get_contract_with_entries() -> Contract {
+        set_context(raffle_account_id(), NearToken::from_yoctonear(0));
+        let mut contract = Contract::new(rng_account_id(), TICKET_PRICE, ROUND_BLOCKS);
+
+        for account_id in [accounts(2), accounts(3), accounts(4)] {
+            set_context(account_id, TICKET_PRICE);
+            contract.enter();
+        }
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must equal the ticket price")]
+    fn test_enter_with_wrong_deposit() {
+        let mut contract = get_contract_with_entries();
+
+        set_context(accounts(5), NearToken::from_millinear(1));
+        contract.enter();
+    }
+
+    #[test]
+    #[should_panic(expected = "No entries")]
+    fn test_draw_without_entries() {
+        set_context(raffle_account_id(), NearToken::from_yoctonear(0));
+        let mut contract = Contract::new(rng_account_id(), TICKET_PRICE, ROUND_BLOCKS);
+        contract.draw();
+    }
+
+    #[test]
+    #[should_panic(expected = "Round can't be drawn before it ends")]
+    fn test_draw_before_round_ends() {
+        let mut contract = get_contract_with_entries();
+
+        set_context_at_height(accounts(2), MIN_REQUEST_DEPOSIT, ROUND_BLOCKS - 1);
+        contract.draw();
+    }
+
+    #[test]
+    fn test_draw_full_round_before_it_ends() {
+        set_context(raffle_account_id(), NearToken::from_yoctonear(0));
+        let mut contract = Contract::new(rng_account_id(), TICKET_PRICE, ROUND_BLOCKS);
+        set_context(accounts(2), TICKET_PRICE);
+        for _ in 0..MAX_ENTRIES {
+            contract.enter();
+        }
+
+        set_context(accounts(2), MIN_REQUEST_DEPOSIT);
+        contract.draw();
+        assert_eq!(contract.get_status(), RoundStatus::Drawing);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must equal the RNG deposit")]
+    fn test_draw_without_rng_deposit() {
+        let mut contract = get_contract_with_entries();
+
+        set_context_at_height(accounts(2), NearToken::from_yoctonear(0), ROUND_BLOCKS);
+        contract.draw();
+    }
+
+    #[test]
+    fn test_draw() {
+        let mut contract = get_contract_with_entries();
+
+        draw_round(&mut contract);
+        assert_eq!(contract.get_status(), RoundStatus::Drawing);
+
+        let random_number = vec![7; 32];
+        let result = rng_result(Ok(RequestOutcome::Fulfilled(random_number.clone())));
+
+        set_context(raffle_account_id(), NearToken::from_yoctonear(0));
+        contract.on_tee_rng_response(1, Ok(result));
+
+        let winner = contract.get_winner(1).unwrap();
+        let expected_winner_id = RandomBytes::new(&random_number)
+            .pick(&[accounts(2), accounts(3), accounts(4)])
+            .unwrap()
+            .clone();
+        assert_eq!(winner.winner_id, expected_winner_id);
+        assert_eq!(winner.random_number, random_number);
+        assert_eq!(winner.prize, TICKET_PRICE.saturating_mul(3));
+
+        assert_eq!(contract.get_round_id(), 2);
+        assert_eq!(contract.get_status(), RoundStatus::Open);
+        assert!(contract.get_entries(0, 10).is_empty());
+    }
+
+    #[test]
+    fn test_draw_timed_out() {
+        let mut contract = get_contract_with_entries();
+
+        draw_round(&mut contract);
+
+        // The yielded request times out without a response
+        let result = rng_result(Err(PromiseError::Failed));
+        assert_eq!(result, None);
+
+        set_context(raffle_account_id(), NearToken::from_yoctonear(0));
+        contract.on_tee_rng_response(1, Ok(result));
+
+        assert!(contract.get_winner(1).is_none());
+        assert_eq!(contract.get_round_id(), 1);
+        assert_eq!(contract.get_status(), RoundStatus::Refunding);
+
+        // Anyone can credit the refunds in batches
+        set_context(accounts(5), NearToken::from_yoctonear(0));
+        assert_eq!(contract.refund_entries(2), 2);
+        assert_eq!(contract.get_status(), RoundStatus::Refunding);
+        assert_eq!(contract.refund_entries(10), 1);
+
+        assert_eq!(contract.get_round_id(), 2);
+        assert_eq!(contract.get_status(), RoundStatus::Open);
+        assert!(contract.get_entries(0, 10).is_empty());
+        for account_id in [accounts(2), accounts(3), accounts(4)] {
+            assert_eq!(contract.get_refund(account_id), TICKET_PRICE);
+        }
+    }
+
+    #[test]
+    fn test_withdraw() {
+        set_context(raffle_account_id(), NearToken::from_yoctonear(0));
+        let mut contract = Contract::new(rng_account_id(), TICKET_PRICE, ROUND_BLOCKS);
+        for _ in 0..2 {
+            set_context(accounts(2), TICKET_PRICE);
+            contract.enter();
+        }
+
+        draw_round(&mut contract);
+        set_context(raffle_account_id(), NearToken::from_yoctonear(0));
+        contract.on_tee_rng_response(1, Ok(None));
+        contract.refund_entries(10);
+        assert_eq!(
+            contract.get_refund(accounts(2)),
+            TICKET_PRICE.saturating_mul(2)
+        );
+
+        set_context(accounts(2), NearToken::from_yoctonear(0));
+        contract.withdraw();
+        assert_eq!(
+            contract.get_refund(accounts(2)),
+            NearToken::from_yoctonear(0)
+        );
+
+        let transfers: Vec<NearToken> = get_created_receipts()
+            .into_iter()
+            .filter_map(|action| match action {
+                MockAction::Transfer { deposit, .. } => Some(deposit),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(transfers, vec![TICKET_PRICE.saturating_mul(2)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "No refund")]
+    fn test_withdraw_without_refund() {
+        let mut contract = get_contract_with_entries();
+
+        set_context(accounts(2), NearToken::from_yoctonear(0));
+        contract.withdraw();
+    }
+
+    #[test]
+    fn test_reset_stuck_round() {
+        let mut contract = get_contract_with_entries();
+
+        draw_round(&mut contract);
+
+        // The draw never called back
+        set_context_at_height(
+            accounts(5),
+            NearToken::from_yoctonear(0),
+            ROUND_BLOCKS + REQUEST_EXPIRATION_BLOCKS + 1,
+        );
+        contract.reset_round();
+        assert_eq!(contract.get_status(), RoundStatus::Refunding);
+        assert_eq!(contract.refund_entries(10), 3);
+
+        assert_eq!(contract.get_round_id(), 2);
+        assert_eq!(contract.get_status(), RoundStatus::Open);
+        assert_eq!(contract.get_refund(accounts(3)), TICKET_PRICE);
+    }
+
+    #[test]
+    #[should_panic(expected = "Round can't be reset before the RNG request expires")]
+    fn test_reset_round_before_expiration() {
+        let mut contract = get_contract_with_entries();
+
+        draw_round(&mut contract);
+
+        set_context_at_height(
+            accounts(5),
+            NearToken::from_yoctonear(0),
+            ROUND_BLOCKS + REQUEST_EXPIRATION_BLOCKS,
+        );
+        contract.reset_round();
+    }
+
+    #[test]
+    #[should_panic(expected = "Round is not being refunded")]
+    fn test_refund_entries_of_open_round() {
+        let mut contract = get_contract_with_entries();
+        contract.refund_entries(10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Round is not open")]
+    fn test_enter_while_drawing() {
+        let mut contract = get_contract_with_entries();
+
+        draw_round(&mut contract);
+
+        set_context(accounts(5), TICKET_PRICE);
+        contract.enter();
+    }
+}
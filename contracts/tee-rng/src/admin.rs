@@ -3,19 +3,241 @@ use near_sdk::near;
 
 #[near]
 impl Contract {
-    pub fn approve_codehash(&mut self, codehash: String) {
+    /// Propose a new owner, who has to accept the ownership to complete the transfer
+    pub fn propose_owner(&mut self, new_owner_id: AccountId) {
         self.assert_owner();
-        self.approved_codehashes.insert(codehash);
+        self.proposed_owner_id = Some(new_owner_id.clone());
+
+        Event::OwnershipTransferProposed {
+            owner_id: env::predecessor_account_id(),
+            proposed_owner_id: new_owner_id,
+        }
+        .emit();
+    }
+
+    pub fn accept_ownership(&mut self) {
+        let new_owner_id = env::predecessor_account_id();
+        require!(
+            self.proposed_owner_id.as_ref() == Some(&new_owner_id),
+            "Only the proposed owner can accept the ownership"
+        );
+        let previous_owner_id = self.owner_id.replace(new_owner_id.clone()).unwrap();
+        self.proposed_owner_id = None;
+
+        // The roles of the previous owner move to the new owner, which starts with all of them.
+        // Approvers are only changed by the council, so the previous owner stays an approver.
+        for role in owner_roles() {
+            self.internal_revoke_role(previous_owner_id.clone(), role);
+            self.internal_grant_role(new_owner_id.clone(), role);
+        }
+
+        Event::OwnershipTransferred {
+            previous_owner_id,
+            new_owner_id,
+        }
+        .emit();
+    }
+
+    pub fn cancel_ownership_transfer(&mut self) {
+        self.assert_owner();
+        if let Some(proposed_owner_id) = self.proposed_owner_id.take() {
+            Event::OwnershipTransferCancelled { proposed_owner_id }.emit();
+        }
+    }
+
+    /// Give up the ownership and the owner's roles, which disables the owner's admin methods
+    /// permanently. Roles granted to other accounts are kept, e.g. to pause in an emergency, and
+    /// so is the owner's seat in the council of approvers.
+    #[payable]
+    pub fn renounce_ownership(&mut self) {
+        assert_one_yocto();
+        self.assert_owner();
+        let previous_owner_id = self.owner_id.take().unwrap();
+        self.proposed_owner_id = None;
+        for role in owner_roles() {
+            self.internal_revoke_role(previous_owner_id.clone(), role);
+        }
+
+        Event::OwnershipRenounced { previous_owner_id }.emit();
+    }
+
+    /// Grant a role other than `CodehashManager`, whose holders are changed by council proposals
+    pub fn grant_role(&mut self, account_id: AccountId, role: Role) {
+        self.assert_owner();
+        assert_owner_role(role);
+        self.internal_grant_role(account_id, role);
+    }
+
+    pub fn revoke_role(&mut self, account_id: AccountId, role: Role) {
+        self.assert_owner();
+        assert_owner_role(role);
+        self.internal_revoke_role(account_id, role);
+    }
+
+    /// Fail stuck requests and refund their deposits, e.g. when workers are unavailable
+    pub fn fail_requests(&mut self, request_ids: Vec<u64>) {
+        self.assert_owner();
+        for request_id in request_ids {
+            // Timed out requests were already refunded and are left for purging
+            let timed_out = self
+                .pending_requests
+                .get(&request_id)
+                .is_some_and(|request| self.is_request_timed_out(request));
+            if timed_out {
+                continue;
+            }
+            if let Some(request) = self.internal_remove_pending_request(request_id) {
+                self.internal_resume_request(&request, RequestOutcome::Failed);
+                self.internal_complete_request(request_id, RequestStatus::Failed, None);
+
+                Event::RequestFailed {
+                    account_id: request.account_id,
+                    request_id,
+                }
+                .emit();
+            }
+        }
+    }
+
+    /// Set the number of blocks a proposal can be voted on
+    pub fn set_proposal_expiry_blocks(&mut self, blocks: u64) {
+        self.assert_owner();
+        self.proposal_expiry_blocks = blocks;
+
+        Event::ProposalExpiryBlocksUpdated { blocks }.emit();
+    }
+
+    /// Set the number of blocks between staging and deploying an upgrade
+    pub fn set_upgrade_delay_blocks(&mut self, blocks: u64) {
+        self.assert_owner();
+        require!(
+            blocks >= MIN_UPGRADE_DELAY_BLOCKS,
+            format!(
+                "Upgrade delay must be at least {} blocks",
+                MIN_UPGRADE_DELAY_BLOCKS
+            )
+        );
+        self.upgrade_delay_blocks = blocks;
+
+        Event::UpgradeDelayBlocksUpdated { blocks }.emit();
     }
 
-    pub fn change_owner(&mut self, new_owner_id: AccountId) {
+    /// Set the number of blocks fulfilled results are kept for consumers to claim
+    pub fn set_result_retention_blocks(&mut self, blocks: u64) {
         self.assert_owner();
-        self.owner_id = new_owner_id;
+        self.result_retention_blocks = blocks;
+
+        Event::ResultRetentionBlocksUpdated { blocks }.emit();
+    }
+
+    /// Accept a fungible token as payment for requests at the given price per request
+    pub fn set_ft_price(&mut self, token_id: AccountId, price: U128) {
+        self.assert_role(Role::Treasurer);
+        require!(price.0 > 0, "Price must be positive");
+        self.ft_prices.insert(token_id.clone(), price);
+
+        Event::FtPriceSet { token_id, price }.emit();
+    }
+
+    pub fn remove_ft_price(&mut self, token_id: AccountId) {
+        self.assert_role(Role::Treasurer);
+        if self.ft_prices.remove(&token_id).is_some() {
+            Event::FtPriceRemoved { token_id }.emit();
+        }
+    }
+
+    /// Withdraw fungible tokens collected from fulfilled requests
+    #[payable]
+    pub fn withdraw_ft_fees(
+        &mut self,
+        token_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> Promise {
+        assert_one_yocto();
+        self.assert_role(Role::Treasurer);
+
+        let fees = self.ft_fees.get_mut(&token_id).expect("No fees collected");
+        require!(fees.0 >= amount.0, "Not enough fees collected");
+        fees.0 -= amount.0;
+
+        Event::FtFeesWithdrawn {
+            token_id: token_id.clone(),
+            receiver_id: receiver_id.clone(),
+            amount,
+        }
+        .emit();
+
+        self.internal_ft_transfer(token_id.clone(), receiver_id, amount)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(ON_FT_FEES_WITHDRAWN_CALL_GAS)
+                    .on_ft_fees_withdrawn(token_id, amount),
+            )
+    }
+
+    /// Restore the collected fees if the withdrawal failed
+    #[private]
+    pub fn on_ft_fees_withdrawn(&mut self, token_id: AccountId, amount: U128) {
+        if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            let fees = self.ft_fees.entry(token_id.clone()).or_insert(U128(0));
+            fees.0 += amount.0;
+
+            Event::FtFeesWithdrawalFailed { token_id, amount }.emit();
+        }
     }
 }
 
 impl Contract {
     pub(crate) fn assert_owner(&mut self) {
-        require!(env::predecessor_account_id() == self.owner_id);
+        let owner_id = self
+            .owner_id
+            .as_ref()
+            .expect("Ownership has been renounced");
+        require!(env::predecessor_account_id() == *owner_id, "Not the owner");
+    }
+
+    pub(crate) fn internal_grant_role(&mut self, account_id: AccountId, role: Role) {
+        let roles = self.roles.entry(account_id.clone()).or_default();
+        if !roles.contains(&role) {
+            roles.push(role);
+
+            Event::RoleGranted { account_id, role }.emit();
+        }
+    }
+
+    pub(crate) fn internal_revoke_role(&mut self, account_id: AccountId, role: Role) {
+        if let Some(roles) = self.roles.get_mut(&account_id) {
+            if roles.contains(&role) {
+                roles.retain(|r| *r != role);
+                if roles.is_empty() {
+                    self.roles.remove(&account_id);
+                }
+
+                Event::RoleRevoked { account_id, role }.emit();
+            }
+        }
+    }
+
+    pub(crate) fn assert_role(&self, role: Role) {
+        require!(
+            self.has_role(env::predecessor_account_id(), role),
+            format!("Missing role {:?}", role)
+        );
     }
 }
+
+/// Roles the owner can grant and revoke. The approvers holding `CodehashManager` are changed by
+/// council proposals instead.
+fn owner_roles() -> impl Iterator<Item = Role> {
+    Role::ALL
+        .into_iter()
+        .filter(|role| *role != Role::CodehashManager)
+}
+
+fn assert_owner_role(role: Role) {
+    require!(
+        role != Role::CodehashManager,
+        "Approvers are changed by council proposals"
+    );
+}
@@ -0,0 +1,53 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+/// Details about a worker image, provided when proposing to approve its codehash
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct CodehashDetails {
+    pub label: String,
+    pub worker_version: String,
+    /// Commit of the worker source the image is built from
+    pub source_commit: String,
+    /// Block timestamp in nanoseconds after which the codehash is no longer approved
+    pub expires_at: Option<U64>,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct CodehashMetadata {
+    pub label: String,
+    pub worker_version: String,
+    pub source_commit: String,
+    pub approved_at: U64,
+    /// Approvers who voted for the codehash
+    pub approved_by: Vec<AccountId>,
+    pub expires_at: Option<U64>,
+}
+
+impl CodehashMetadata {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| env::block_timestamp() > expires_at.0)
+    }
+}
+
+/// Codehashes are the lowercase hex encoded sha256 digest of the worker image, as extracted
+/// from quotes, so other spellings would never match a registering worker
+pub fn assert_valid_codehash(codehash: &str) {
+    require!(
+        codehash.len() == 64
+            && codehash
+                .chars()
+                .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)),
+        "Codehash must be 64 lowercase hex characters"
+    );
+}
+
+impl Contract {
+    pub(crate) fn is_codehash_approved(&self, codehash: &String) -> bool {
+        self.approved_codehashes
+            .get(codehash)
+            .is_some_and(|metadata| !metadata.is_expired())
+    }
+}
@@ -1,47 +1,94 @@
-use near_sdk::serde::Serialize;
-use near_sdk::serde_json::json;
-use near_sdk::{log, AccountId, PublicKey};
-
-pub const EVENT_STANDARD: &str = "tee-rng";
-pub const EVENT_STANDARD_VERSION: &str = "1.0.0";
-
-#[derive(Serialize)]
-#[serde(
-    crate = "near_sdk::serde",
-    rename_all = "snake_case",
-    tag = "event",
-    content = "data"
-)]
-#[must_use = "Don't forget to `.emit()` this event"]
-pub enum Event<'a> {
-    WorkerRegistered {
-        worker_id: &'a AccountId,
-        public_key: &'a PublicKey,
-        codehash: &'a String,
-        checksum: &'a String,
-    },
-    Request {
-        account_id: &'a AccountId,
-        request_id: &'a u64,
-        random_seed: &'a [u8],
-    },
-    Response {
-        worker_id: &'a AccountId,
-        request_id: &'a u64,
-        random_number: &'a [u8],
-    },
+use near_sdk::env;
+use tee_rng_events::format_event_log;
+
+pub use tee_rng_events::Event;
+
+/// Log events in the format indexers parse with `tee_rng_events::parse_event_log`
+pub trait EmitEvent {
+    fn emit(&self);
+}
+
+impl EmitEvent for Event {
+    fn emit(&self) {
+        env::log_str(&format_event_log(self));
+    }
 }
 
-impl Event<'_> {
-    pub fn emit(&self) {
-        let json = json!(self);
-        let event_json = json!({
-            "standard": EVENT_STANDARD,
-            "version": EVENT_STANDARD_VERSION,
-            "event": json["event"],
-            "data": [json["data"]]
-        })
-        .to_string();
-        log!("EVENT_JSON:{}", event_json);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CodehashAction, CouncilAction, PausableMethod, Payment};
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::{accounts, get_logs, VMContextBuilder};
+    use near_sdk::{testing_env, NearToken, PublicKey};
+    use tee_rng_events::{parse_event_log, EVENT_STANDARD_VERSION};
+
+    fn assert_round_trip(event: Event) {
+        testing_env!(VMContextBuilder::new().build());
+        event.emit();
+        let logs = get_logs();
+        assert_eq!(logs.len(), 1);
+
+        let event_log = parse_event_log(&logs[0]).unwrap();
+        assert_eq!(event_log.version, EVENT_STANDARD_VERSION);
+        assert_eq!(event_log.to_string(), logs[0]);
+        assert_eq!(event_log.event, event);
+    }
+
+    #[test]
+    fn test_request_event_round_trip() {
+        assert_round_trip(Event::Request {
+            account_id: accounts(1),
+            request_id: 1,
+            random_seed: vec![1, 2, 3],
+            payment: Payment::Near(NearToken::from_millinear(5)),
+            block_height: 100,
+        });
+    }
+
+    #[test]
+    fn test_response_event_round_trip() {
+        assert_round_trip(Event::Response {
+            worker_id: accounts(2),
+            request_id: 1,
+            random_number: vec![4, 5, 6],
+            signature: vec![7; 64],
+        });
+    }
+
+    #[test]
+    fn test_worker_registered_event_round_trip() {
+        let public_key: PublicKey = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp"
+            .parse()
+            .unwrap();
+        assert_round_trip(Event::WorkerRegistered {
+            worker_id: accounts(2),
+            public_key,
+            codehash: "0".repeat(64),
+            checksum: "checksum".to_string(),
+        });
+    }
+
+    #[test]
+    fn test_admin_event_round_trip() {
+        assert_round_trip(Event::CodehashProposed {
+            proposal_id: 1,
+            proposer: accounts(0),
+            action: CodehashAction::Revoke,
+            codehash: "0".repeat(64),
+        });
+        assert_round_trip(Event::CouncilProposed {
+            proposal_id: 2,
+            proposer: accounts(0),
+            action: CouncilAction::AddApprover(accounts(5)),
+        });
+        assert_round_trip(Event::FtFeesWithdrawn {
+            token_id: accounts(3),
+            receiver_id: accounts(0),
+            amount: U128(10),
+        });
+        assert_round_trip(Event::Paused {
+            method: PausableMethod::Respond,
+        });
     }
 }
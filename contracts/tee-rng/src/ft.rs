@@ -0,0 +1,60 @@
+use crate::*;
+use near_sdk::serde_json::json;
+use tee_rng_sdk::FtTransferMessage;
+
+#[near]
+impl Contract {
+    /// Request a random number by transferring whitelisted fungible tokens to the contract.
+    /// Tokens above the configured price are returned to the sender as unused.
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let token_id = env::predecessor_account_id();
+        let price = self
+            .ft_prices
+            .get(&token_id)
+            .copied()
+            .expect("Token is not accepted");
+        require!(amount.0 >= price.0, "Transferred amount is less than price");
+
+        let message: FtTransferMessage =
+            serde_json::from_str(&msg).expect("Invalid transfer message");
+        match message {
+            FtTransferMessage::Request {} => {
+                self.internal_request(
+                    sender_id,
+                    Payment::FungibleToken {
+                        token_id,
+                        amount: price,
+                    },
+                );
+            }
+        }
+
+        PromiseOrValue::Value(U128(amount.0 - price.0))
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_ft_transfer(
+        &self,
+        token_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> Promise {
+        Promise::new(token_id).function_call(
+            "ft_transfer".into(),
+            json!({
+                "receiver_id": receiver_id,
+                "amount": amount,
+            })
+            .to_string()
+            .into_bytes(),
+            NearToken::from_yoctonear(1),
+            FT_TRANSFER_CALL_GAS,
+        )
+    }
+}
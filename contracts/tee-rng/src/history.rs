@@ -0,0 +1,109 @@
+use crate::*;
+
+// Number of the most recently fulfilled requests which can be listed
+pub const RECENT_FULFILLED_CAPACITY: u64 = 100;
+
+#[near]
+impl Contract {
+    /// Remove records of requests completed longer than the retention period ago, with their
+    /// results if they weren't claimed. Only the `limit` oldest records are checked. The caller
+    /// receives the bounty retained from the deposit of each fulfilled or cancelled request.
+    pub fn purge_request_history(&mut self, limit: u32) -> u32 {
+        let expired_records: Vec<RequestRecord> = self
+            .request_records
+            .iter()
+            .take(limit as usize)
+            .map(|(_, record)| record)
+            .filter(|record| self.is_record_expired(record))
+            .collect();
+
+        let mut bounty = NearToken::from_yoctonear(0);
+        for record in expired_records.iter() {
+            self.request_records.remove(&record.request_id);
+            self.account_requests
+                .remove(&(record.account_id.clone(), record.request_id));
+            if self.fulfilled_results.remove(&record.request_id).is_some() {
+                Event::ResultPurged {
+                    request_id: record.request_id,
+                }
+                .emit();
+            }
+            let bounty_retained = matches!(record.payment, Payment::Near(_))
+                && matches!(
+                    record.status,
+                    RequestStatus::Fulfilled | RequestStatus::Cancelled
+                );
+            if bounty_retained {
+                bounty = bounty.saturating_add(PURGE_BOUNTY);
+            }
+
+            Event::RequestHistoryPurged {
+                request_id: record.request_id,
+            }
+            .emit();
+        }
+
+        if !bounty.is_zero() {
+            Promise::new(env::predecessor_account_id()).transfer(bounty);
+        }
+
+        expired_records.len() as u32
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_record_request(&mut self, request: &Request) {
+        let record = RequestRecord {
+            request_id: request.request_id,
+            account_id: request.account_id.clone(),
+            payment: request.payment.clone(),
+            random_seed: request.random_seed.clone(),
+            block_height: request.block_height,
+            status: RequestStatus::Pending,
+            worker_id: None,
+            completed_at: None,
+        };
+        self.internal_record_stats(&record);
+        self.request_records.insert(&request.request_id, &record);
+        self.account_requests
+            .insert(&(request.account_id.clone(), request.request_id), &());
+    }
+
+    /// Update the status of a pending request. Completed requests keep their status, e.g. a
+    /// timed out request answered late.
+    pub(crate) fn internal_complete_request(
+        &mut self,
+        request_id: u64,
+        status: RequestStatus,
+        worker_id: Option<AccountId>,
+    ) {
+        let Some(mut record) = self.request_records.get(&request_id) else {
+            return;
+        };
+        if record.is_completed() {
+            return;
+        }
+
+        record.status = status;
+        record.worker_id = worker_id;
+        record.completed_at = Some(env::block_height());
+        self.request_records.insert(&request_id, &record);
+
+        if status == RequestStatus::Fulfilled {
+            // Keep the ids of recently fulfilled requests in a ring buffer
+            if (self.recent_fulfilled.len() as u64) < RECENT_FULFILLED_CAPACITY {
+                self.recent_fulfilled.push(request_id);
+            } else {
+                let index = self.stats.fulfilled % RECENT_FULFILLED_CAPACITY;
+                self.recent_fulfilled.set(index as u32, request_id);
+            }
+        }
+        self.internal_record_stats(&record);
+    }
+
+    fn is_record_expired(&self, record: &RequestRecord) -> bool {
+        record.completed_at.is_some_and(|completed_at| {
+            env::block_height() > completed_at + self.result_retention_blocks
+        })
+    }
+}
@@ -1,31 +1,72 @@
-use dcap_qvl::{verify, QuoteCollateralV3};
-use hex::{decode, encode};
+use dcap_qvl::QuoteCollateralV3;
+use hex::encode;
 use near_sdk::{
     assert_one_yocto,
+    collections::TreeMap,
     env::{self, block_timestamp},
+    json_types::U128,
     log, near, require,
-    store::{IterableMap, IterableSet},
+    store::{IterableMap, Vector},
     AccountId, BorshStorageKey, CryptoHash, Gas, GasWeight, NearToken, PanicOnDefault, Promise,
-    PromiseError, PromiseOrValue, PublicKey,
+    PromiseError, PromiseIndex, PromiseOrValue, PromiseResult, PublicKey,
 };
-use sha3::Digest as KeccakDigest;
+use tee_rng_verifier as verifier;
 
+use crate::codehash::*;
 use crate::events::*;
+use crate::history::*;
+use crate::migration::*;
+use crate::proposal::*;
+use crate::upgrade::*;
+pub use tee_rng_sdk::{
+    CodehashAction, CouncilAction, FulfilledResult, PausableMethod, Payment, RegistrationReport,
+    Request, RequestRecord, RequestStats, RequestStatus, Response, ResponseVerification, Role,
+    StatsView, Worker, WorkerAttestation, WorkerStats, WorkerStatsView, YieldIndex,
+    MIN_REQUEST_DEPOSIT, REQUEST_EXPIRATION_BLOCKS,
+};
 
 mod admin;
+mod codehash;
 mod collateral;
 mod events;
+mod ft;
+mod history;
+mod migration;
+mod pause;
+mod proposal;
+mod signature;
+mod stats;
 mod upgrade;
 mod view;
 
 // Register used to receive data id from `promise_await_data`.
 const DATA_ID_REGISTER: u64 = 0;
 
-// Minimum attached deposit for a request
-const MIN_REQUEST_DEPOSIT: NearToken = NearToken::from_millinear(5);
-
 // Prepaid gas for a `on_received_response` call
-const ON_RECEIVED_RESPONSE_CALL_GAS: Gas = Gas::from_tgas(5);
+const ON_RECEIVED_RESPONSE_CALL_GAS: Gas = Gas::from_tgas(10);
+
+// Prepaid gas for a `ft_transfer` call when refunding fungible tokens
+const FT_TRANSFER_CALL_GAS: Gas = Gas::from_tgas(5);
+
+// Prepaid gas for a `on_ft_fees_withdrawn` call
+const ON_FT_FEES_WITHDRAWN_CALL_GAS: Gas = Gas::from_tgas(5);
+
+// Part of the deposit retained from requests, paid to whoever purges them from the pending
+// requests or the request history
+const PURGE_BOUNTY: NearToken = NearToken::from_millinear(1);
+
+// Default number of blocks a proposal can be voted on
+const DEFAULT_PROPOSAL_EXPIRY_BLOCKS: u64 = 86_400;
+
+// Default number of blocks between staging and deploying an upgrade
+const DEFAULT_UPGRADE_DELAY_BLOCKS: u64 = 86_400;
+
+// Minimum number of blocks between staging and deploying an upgrade, so users always have time
+// to review the staged code
+const MIN_UPGRADE_DELAY_BLOCKS: u64 = 43_200;
+
+// Default number of blocks fulfilled results are kept for consumers to claim
+const DEFAULT_RESULT_RETENTION_BLOCKS: u64 = 1000;
 
 #[near]
 #[derive(BorshStorageKey)]
@@ -33,48 +74,92 @@ pub enum Prefix {
     ApprovedCodeHashes,
     WorkerByAccountId,
     PendingRequests,
+    FtPrices,
+    FulfilledResults,
+    Roles,
+    FtFees,
+    CodehashProposals,
+    CodehashRegistry,
+    RequestRecords,
+    AccountRequests,
+    RecentFulfilled,
+    PendingRequestIds,
+    WorkerStats,
+    CouncilProposals,
+}
+
+/// Data passed to `on_received_response` when a yielded request is resumed
+#[near(serializers = [json])]
+#[serde(rename_all = "snake_case")]
+pub enum RequestOutcome {
+    Fulfilled(Vec<u8>),
+    Cancelled,
+    Failed,
 }
 
-#[near(serializers = [json, borsh])]
-#[derive(Clone)]
-pub struct Worker {
-    checksum: String,
-    codehash: String,
-    public_key: PublicKey,
+/// Data a yielded request is resumed with. Requests of state version 0 were resumed with the
+/// random number only.
+#[near(serializers = [json])]
+#[serde(untagged)]
+pub enum ResumeData {
+    Outcome(RequestOutcome),
+    V0(Vec<u8>),
 }
 
-/// The index into calling the YieldResume feature of NEAR. This will allow to resume
-/// a yield call after the contract has been called back via this index.
-#[derive(Debug, Clone)]
-#[near(serializers=[borsh, json])]
-pub struct YieldIndex {
-    pub data_id: CryptoHash,
+impl From<RequestOutcome> for ResumeData {
+    fn from(outcome: RequestOutcome) -> Self {
+        Self::Outcome(outcome)
+    }
 }
 
-#[near(serializers = [json, borsh])]
-#[derive(Clone)]
-pub struct Request {
-    request_id: u64,
-    random_seed: Vec<u8>,
-    yield_index: YieldIndex,
+impl From<ResumeData> for RequestOutcome {
+    fn from(data: ResumeData) -> Self {
+        match data {
+            ResumeData::Outcome(outcome) => outcome,
+            ResumeData::V0(random_number) => Self::Fulfilled(random_number),
+        }
+    }
 }
 
-#[near(serializers = [json, borsh])]
-#[derive(Clone)]
-pub struct Response {
-    request_id: u64,
-    random_number: Vec<u8>,
-    signature: Vec<u8>,
+/// Arguments `on_received_response` is called with, as given when the request was yielded.
+/// Requests of state version 0 only passed the requester and the attached deposit.
+#[near(serializers = [json])]
+#[serde(untagged)]
+enum YieldArgs {
+    Request(u64, AccountId, Payment),
+    V0(AccountId, NearToken),
 }
 
 #[near(contract_state)]
 #[derive(PanicOnDefault)]
 pub struct Contract {
-    owner_id: AccountId,
-    approved_codehashes: IterableSet<String>,
+    owner_id: Option<AccountId>,
+    proposed_owner_id: Option<AccountId>,
+    approved_codehashes: IterableMap<String, CodehashMetadata>,
     worker_by_account_id: IterableMap<AccountId, Worker>,
     pending_requests: IterableMap<u64, Request>,
     last_request_id: u64,
+    ft_prices: IterableMap<AccountId, U128>,
+    fulfilled_results: IterableMap<u64, FulfilledResult>,
+    result_retention_blocks: u64,
+    roles: IterableMap<AccountId, Vec<Role>>,
+    ft_fees: IterableMap<AccountId, U128>,
+    paused_methods: Vec<PausableMethod>,
+    codehash_proposals: IterableMap<u64, CodehashProposal>,
+    council_proposals: IterableMap<u64, CouncilProposal>,
+    last_proposal_id: u64,
+    approval_threshold: u32,
+    proposal_expiry_blocks: u64,
+    staged_upgrade: Option<StagedUpgrade>,
+    upgrade_delay_blocks: u64,
+    code_hash: Option<String>,
+    previous_code_hash: Option<String>,
+    request_records: TreeMap<u64, RequestRecord>,
+    account_requests: TreeMap<(AccountId, u64), ()>,
+    recent_fulfilled: Vector<u64>,
+    pending_request_ids: TreeMap<u64, ()>,
+    stats: RequestStats,
+    worker_stats: IterableMap<AccountId, WorkerStats>,
 }
 
 #[near]
@@ -82,13 +167,14 @@ impl Contract {
     #[init]
     #[private]
     pub fn new(owner_id: AccountId) -> Self {
-        Self {
-            owner_id,
-            approved_codehashes: IterableSet::new(Prefix::ApprovedCodeHashes),
-            worker_by_account_id: IterableMap::new(Prefix::WorkerByAccountId),
-            pending_requests: IterableMap::new(Prefix::PendingRequests),
-            last_request_id: 0,
+        write_state_version();
+
+        Event::ContractInitialized {
+            owner_id: owner_id.clone(),
         }
+        .emit();
+
+        Self::with_owner(owner_id)
     }
 
     #[payable]
@@ -101,20 +187,19 @@ impl Contract {
         tcb_info: String,
     ) {
         assert_one_yocto();
+        self.assert_not_paused(PausableMethod::RegisterWorker);
 
         let collateral = collateral::get_collateral(collateral);
-        let quote = decode(quote_hex).unwrap();
         let now = block_timestamp() / 1000000000;
-        let result = verify::verify(&quote, &collateral, now).expect("Report is not verified");
-        let report = result.report.as_td10().unwrap();
+        let result = verifier::verify_quote(&quote_hex, &collateral, now)
+            .unwrap_or_else(|err| env::panic_str(&err));
+        let report = verifier::td_report(&result).unwrap_or_else(|err| env::panic_str(&err));
         let rtmr3 = encode(report.rt_mr3);
 
         // verify the signer public key is the same as the one included in the report data
         let report_data = encode(report.report_data);
         let public_key = env::signer_account_pk();
-        let public_key_str: String = (&public_key).into();
-        // pad the public key hex with 0 to 128 characters
-        let public_key_hex = format!("{:0>128}", encode(public_key_str));
+        let public_key_hex = verifier::report_data_for_public_key(&public_key);
         require!(
             public_key_hex == report_data,
             format!(
@@ -129,7 +214,9 @@ impl Contract {
 
         log!("verify result: {:?}", result);
 
-        self.internal_register_worker(codehash, public_key, checksum);
+        let attestation = verifier::attestation_evidence(&quote_hex, &collateral, &result, now)
+            .unwrap_or_else(|err| env::panic_str(&err));
+        self.internal_register_worker(codehash, public_key, checksum, Some(attestation));
     }
 
     /// Mocked function for testing register worker
@@ -143,11 +230,12 @@ impl Contract {
         tcb_info: String,
     ) {
         assert_one_yocto();
+        self.assert_not_paused(PausableMethod::RegisterWorker);
 
         let public_key = env::signer_account_pk();
-        let codehash = self.approved_codehashes.iter().next().unwrap().clone();
+        let codehash = self.approved_codehashes.keys().next().unwrap().clone();
 
-        self.internal_register_worker(codehash, public_key, checksum.clone());
+        self.internal_register_worker(codehash, public_key, checksum.clone(), None);
 
         log!(
             "register_worker arguments: quote_hex: {:?}, collateral: {:?}, checksum: {:?}, tcb_info: {:?}",
@@ -167,110 +255,66 @@ impl Contract {
             "Attached deposit must be no less than 0.005 NEAR"
         );
 
-        let request_id = self.last_request_id + 1;
-        self.last_request_id = request_id;
-
-        let account_id = env::predecessor_account_id();
-
-        let promise_index = env::promise_yield_create(
-            "on_received_response",
-            &serde_json::to_vec(&(&account_id, &attached_deposit)).unwrap(),
-            ON_RECEIVED_RESPONSE_CALL_GAS,
-            GasWeight(0),
-            DATA_ID_REGISTER,
-        );
-
-        // Store the request in the contract's local state
-        let data_id: CryptoHash = env::read_register(DATA_ID_REGISTER)
-            .expect("read_register failed")
-            .try_into()
-            .expect("conversion to CryptoHash failed");
-
-        self.pending_requests.insert(
-            request_id,
-            Request {
-                request_id,
-                random_seed: env::random_seed(),
-                yield_index: YieldIndex { data_id },
-            },
+        let promise_index = self.internal_request(
+            env::predecessor_account_id(),
+            Payment::Near(attached_deposit),
         );
-
         env::promise_return(promise_index);
-
-        Event::Request {
-            account_id: &account_id,
-            request_id: &request_id,
-            random_seed: &env::random_seed(),
-        }
-        .emit();
     }
 
     /// A worker inside TEE will call the function with a response to the request
     pub fn respond(&mut self, response: Response) {
+        self.assert_not_paused(PausableMethod::Respond);
         let request_id = response.request_id;
         let request = self
             .pending_requests
             .get(&request_id)
             .expect("Request not found");
+        // The requester of a timed out request was already refunded
+        require!(!self.is_request_timed_out(request), "Request timed out");
         let worker = self.require_approved_worker();
-        let public_key = worker.public_key.clone();
+        let public_key = signature::ed25519_public_key_bytes(&worker.public_key)
+            .unwrap_or_else(|err| env::panic_str(&err));
 
-        let signature: &[u8; 64] = response
-            .signature
-            .as_slice()
-            .try_into()
-            .expect("Signature must be 64 bytes");
-        let public_key_bytes_slice = public_key.as_bytes();
-
-        // NEAR public keys include a curve type prefix (1 byte) + actual key (32 bytes)
-        // For ED25519, the first byte should be 0
-        let public_key_bytes: &[u8; 32] =
-            if public_key_bytes_slice.len() == 33 && public_key_bytes_slice[0] == 0 {
-                // Extract the actual 32-byte public key (skip the first byte)
-                public_key_bytes_slice[1..33]
-                    .try_into()
-                    .expect("Failed to extract 32-byte public key")
-            } else if public_key_bytes_slice.len() == 32 {
-                // Already 32 bytes, use as is
-                public_key_bytes_slice
-                    .try_into()
-                    .expect("Public key must be 32 bytes")
-            } else {
-                // Unexpected format
-                env::panic_str(&format!(
-                    "Unexpected public key format: {} bytes, first byte: {}",
-                    public_key_bytes_slice.len(),
-                    public_key_bytes_slice.first().unwrap_or(&255)
-                ));
-            };
-
-        // Create the message hash that was signed: keccak256(keccak256(requestId, seed, random))
-        let mut message_hasher = sha3::Keccak256::new();
-        message_hasher.update(request_id.to_le_bytes()); // requestId
-        message_hasher.update(&request.random_seed); // seed
-        message_hasher.update(&response.random_number); // random
-        let first_hash = message_hasher.finalize();
+        require!(response.signature.len() == 64, "Signature must be 64 bytes");
+        // verify response is signed by the worker's public key
+        require!(
+            signature::verify_response_signature(
+                &public_key,
+                request_id,
+                &request.random_seed,
+                &response.random_number,
+                &response.signature,
+            ),
+            "Invalid signature"
+        );
 
-        // Double hash: keccak256(keccak256(...))
-        let mut double_hasher = sha3::Keccak256::new();
-        double_hasher.update(first_hash);
-        let message_hash = double_hasher.finalize();
+        if let Some(request) = self.internal_remove_pending_request(request_id) {
+            self.internal_resume_request(
+                &request,
+                RequestOutcome::Fulfilled(response.random_number.clone()),
+            );
 
-        // verify response is signed by the worker's public key
-        env::ed25519_verify(signature, &message_hash, public_key_bytes);
-
-        // First get the yield promise of the (potentially timed out) request.
-        if let Some(request) = self.pending_requests.remove(&request_id) {
-            // Finally, resolve the promise. This will have no effect if the request already timed.
-            env::promise_yield_resume(
-                &request.yield_index.data_id,
-                &serde_json::to_vec(&response.random_number).unwrap(),
+            self.fulfilled_results.insert(
+                request_id,
+                FulfilledResult {
+                    request_id,
+                    account_id: request.account_id,
+                    random_number: response.random_number.clone(),
+                    block_height: env::block_height(),
+                },
+            );
+            self.internal_complete_request(
+                request_id,
+                RequestStatus::Fulfilled,
+                Some(env::predecessor_account_id()),
             );
 
             Event::Response {
-                worker_id: &env::predecessor_account_id(),
-                request_id: &request_id,
-                random_number: &response.random_number,
+                worker_id: env::predecessor_account_id(),
+                request_id,
+                random_number: response.random_number,
+                signature: response.signature,
             }
             .emit();
         } else {
@@ -278,30 +322,338 @@ impl Contract {
         }
     }
 
-    /// Combine the random seed generated on-chain with the TEE generated random seed
+    /// Cancel a pending request. The deposit is refunded once the yielded promise is resumed.
+    pub fn cancel_request(&mut self, request_id: u64) {
+        let account_id = env::predecessor_account_id();
+        let request = self
+            .pending_requests
+            .get(&request_id)
+            .expect("Request not found");
+        require!(
+            request.account_id == account_id,
+            "Only the requester can cancel the request"
+        );
+        require!(
+            !self.is_request_timed_out(&request),
+            "Request has already timed out"
+        );
+        let request = self.internal_remove_pending_request(request_id).unwrap();
+        self.internal_resume_request(&request, RequestOutcome::Cancelled);
+        self.internal_complete_request(request_id, RequestStatus::Cancelled, None);
+
+        Event::RequestCancelled {
+            account_id,
+            request_id,
+        }
+        .emit();
+    }
+
+    /// Remove requests which have already timed out from the pending requests. Only the
+    /// `limit` oldest requests are checked, up to the first one which hasn't expired yet.
+    /// The caller receives a bounty retained from the deposit of each purged request.
+    pub fn purge_expired_requests(&mut self, limit: u32) -> u32 {
+        // Request ids increase with the block height, so the expired requests come first
+        let expired_request_ids: Vec<u64> = self
+            .pending_request_ids
+            .iter()
+            .take(limit as usize)
+            .map(|(request_id, _)| request_id)
+            .take_while(|request_id| {
+                self.pending_requests
+                    .get(request_id)
+                    .is_some_and(|request| request.is_expired_at(env::block_height()))
+            })
+            .collect();
+
+        let mut bounty = NearToken::from_yoctonear(0);
+        for request_id in expired_request_ids.iter() {
+            let request = self.internal_remove_pending_request(*request_id).unwrap();
+            if let Payment::Near(_) = request.payment {
+                bounty = bounty.saturating_add(PURGE_BOUNTY);
+            }
+            self.internal_complete_request(*request_id, RequestStatus::TimedOut, None);
+
+            Event::RequestPurged {
+                request_id: request.request_id,
+            }
+            .emit();
+        }
+
+        if !bounty.is_zero() {
+            Promise::new(env::predecessor_account_id()).transfer(bounty);
+        }
+
+        expired_request_ids.len() as u32
+    }
+
+    /// Claim the result of a fulfilled request, e.g. after the requester's callback failed.
+    /// The stored result is removed and returned as hex.
+    pub fn claim_result(&mut self, request_id: u64) -> String {
+        let result = self
+            .fulfilled_results
+            .get(&request_id)
+            .expect("Result not found");
+        require!(
+            result.account_id == env::predecessor_account_id(),
+            "Only the requester can claim the result"
+        );
+        require!(!self.is_result_expired(result), "Result has expired");
+
+        let result = self.fulfilled_results.remove(&request_id).unwrap();
+
+        Event::ResultClaimed {
+            account_id: result.account_id,
+            request_id,
+        }
+        .emit();
+
+        encode(result.random_number)
+    }
+
+    /// Remove fulfilled results which are kept longer than the retention period. Only the
+    /// first `limit` stored results are checked.
+    pub fn purge_expired_results(&mut self, limit: u32) -> u32 {
+        let expired_request_ids: Vec<u64> = self
+            .fulfilled_results
+            .values()
+            .take(limit as usize)
+            .filter(|result| self.is_result_expired(result))
+            .map(|result| result.request_id)
+            .collect();
+
+        for request_id in expired_request_ids.iter() {
+            self.fulfilled_results.remove(request_id);
+
+            Event::ResultPurged {
+                request_id: *request_id,
+            }
+            .emit();
+        }
+
+        expired_request_ids.len() as u32
+    }
+
+    /// Combine the random seed generated on-chain with the TEE generated random seed.
+    /// The arguments are parsed by hand, as requests yielded before the state was versioned
+    /// are resumed with different arguments.
     #[private]
     pub fn on_received_response(
         &mut self,
-        account_id: AccountId,
-        attached_deposit: NearToken,
-        #[callback_result] resp: Result<Vec<u8>, PromiseError>,
+        #[callback_result] resp: Result<ResumeData, PromiseError>,
     ) -> PromiseOrValue<Option<String>> {
-        // Return the attached deposit to the requester
-        Promise::new(account_id.clone()).transfer(attached_deposit);
+        let input = env::input().expect("Expected yield arguments");
+        let args: YieldArgs = serde_json::from_slice(&input).expect("Invalid yield arguments");
+        let resp = resp.map(RequestOutcome::from);
 
-        if resp.is_err() {
-            return PromiseOrValue::Value(None);
+        match args {
+            YieldArgs::Request(request_id, account_id, payment) => {
+                self.internal_on_received_response(request_id, account_id, payment, resp)
+            }
+            YieldArgs::V0(account_id, attached_deposit) => {
+                // Requests of version 0 are refunded in full, as before the state was versioned
+                Promise::new(account_id).transfer(attached_deposit);
+                match resp {
+                    Ok(RequestOutcome::Fulfilled(random_number)) => {
+                        PromiseOrValue::Value(Some(encode(random_number)))
+                    }
+                    _ => PromiseOrValue::Value(None),
+                }
+            }
         }
-        PromiseOrValue::Value(Some(encode(resp.unwrap())))
     }
 }
 
 impl Contract {
+    /// Empty state owned by `owner_id`, e.g. to migrate an older state into
+    pub(crate) fn with_owner(owner_id: AccountId) -> Self {
+        let mut roles = IterableMap::new(Prefix::Roles);
+        // The owner starts with all roles and can delegate them afterwards. It's also the only
+        // approver until the council adds more.
+        roles.insert(owner_id.clone(), Role::ALL.to_vec());
+
+        Self {
+            owner_id: Some(owner_id),
+            proposed_owner_id: None,
+            approved_codehashes: IterableMap::new(Prefix::CodehashRegistry),
+            worker_by_account_id: IterableMap::new(Prefix::WorkerByAccountId),
+            pending_requests: IterableMap::new(Prefix::PendingRequests),
+            last_request_id: 0,
+            ft_prices: IterableMap::new(Prefix::FtPrices),
+            fulfilled_results: IterableMap::new(Prefix::FulfilledResults),
+            result_retention_blocks: DEFAULT_RESULT_RETENTION_BLOCKS,
+            roles,
+            ft_fees: IterableMap::new(Prefix::FtFees),
+            paused_methods: vec![],
+            codehash_proposals: IterableMap::new(Prefix::CodehashProposals),
+            council_proposals: IterableMap::new(Prefix::CouncilProposals),
+            last_proposal_id: 0,
+            approval_threshold: 1,
+            proposal_expiry_blocks: DEFAULT_PROPOSAL_EXPIRY_BLOCKS,
+            staged_upgrade: None,
+            upgrade_delay_blocks: DEFAULT_UPGRADE_DELAY_BLOCKS,
+            code_hash: None,
+            previous_code_hash: None,
+            request_records: TreeMap::new(Prefix::RequestRecords),
+            account_requests: TreeMap::new(Prefix::AccountRequests),
+            recent_fulfilled: Vector::new(Prefix::RecentFulfilled),
+            pending_request_ids: TreeMap::new(Prefix::PendingRequestIds),
+            stats: RequestStats::default(),
+            worker_stats: IterableMap::new(Prefix::WorkerStats),
+        }
+    }
+
+    fn internal_on_received_response(
+        &mut self,
+        request_id: u64,
+        account_id: AccountId,
+        payment: Payment,
+        resp: Result<RequestOutcome, PromiseError>,
+    ) -> PromiseOrValue<Option<String>> {
+        let failed = matches!(resp, Ok(RequestOutcome::Failed));
+        let (random_number, timed_out) = match resp {
+            Ok(RequestOutcome::Fulfilled(random_number)) => (Some(random_number), false),
+            Ok(RequestOutcome::Cancelled | RequestOutcome::Failed) => (None, false),
+            Err(_) => (None, true),
+        };
+
+        // Requests keep a bounty for whoever purges them afterwards: timed out requests from the
+        // pending requests, fulfilled and cancelled requests from the request history. Requests
+        // can't be answered while respond is paused, so timed out requests are cleaned up and
+        // refunded in full instead, like failed requests.
+        let retain_bounty = if timed_out && self.is_paused(PausableMethod::Respond) {
+            self.internal_remove_pending_request(request_id);
+            false
+        } else {
+            !failed
+        };
+
+        if timed_out {
+            self.internal_complete_request(request_id, RequestStatus::TimedOut, None);
+
+            Event::RequestTimedOut {
+                account_id: account_id.clone(),
+                request_id,
+            }
+            .emit();
+        }
+
+        let refund = match payment {
+            // Return the attached deposit to the requester
+            Payment::Near(attached_deposit) => {
+                let refund = if retain_bounty {
+                    attached_deposit.saturating_sub(PURGE_BOUNTY)
+                } else {
+                    attached_deposit
+                };
+                Promise::new(account_id.clone()).transfer(refund);
+                Some(Payment::Near(refund))
+            }
+            // Fungible tokens pay for the randomness, so only refund them if the request failed
+            Payment::FungibleToken { token_id, amount } => {
+                if random_number.is_none() {
+                    self.internal_ft_transfer(token_id.clone(), account_id.clone(), amount);
+                    Some(Payment::FungibleToken { token_id, amount })
+                } else {
+                    let fees = self.ft_fees.entry(token_id).or_insert(U128(0));
+                    fees.0 += amount.0;
+                    None
+                }
+            }
+        };
+
+        if let Some(refund) = refund {
+            Event::DepositRefunded {
+                account_id,
+                request_id,
+                payment: refund,
+            }
+            .emit();
+        }
+
+        PromiseOrValue::Value(random_number.map(encode))
+    }
+
+    fn internal_request(&mut self, account_id: AccountId, payment: Payment) -> PromiseIndex {
+        self.assert_not_paused(PausableMethod::Request);
+        // Requests would only time out while workers can't respond, so refund them right away
+        require!(
+            !self.is_paused(PausableMethod::Respond),
+            "Requests are not accepted while responses are paused"
+        );
+
+        let request_id = self.last_request_id + 1;
+        self.last_request_id = request_id;
+
+        let promise_index = env::promise_yield_create(
+            "on_received_response",
+            &serde_json::to_vec(&(&request_id, &account_id, &payment)).unwrap(),
+            ON_RECEIVED_RESPONSE_CALL_GAS,
+            GasWeight(0),
+            DATA_ID_REGISTER,
+        );
+
+        // Store the request in the contract's local state
+        let data_id: CryptoHash = env::read_register(DATA_ID_REGISTER)
+            .expect("read_register failed")
+            .try_into()
+            .expect("conversion to CryptoHash failed");
+
+        Event::Request {
+            account_id: account_id.clone(),
+            request_id,
+            random_seed: env::random_seed(),
+            payment: payment.clone(),
+            block_height: env::block_height(),
+        }
+        .emit();
+
+        let request = Request {
+            request_id,
+            account_id,
+            payment,
+            random_seed: env::random_seed(),
+            yield_index: YieldIndex { data_id },
+            block_height: env::block_height(),
+        };
+        self.internal_record_request(&request);
+        self.pending_requests.insert(request_id, request);
+        self.pending_request_ids.insert(&request_id, &());
+
+        promise_index
+    }
+
+    pub(crate) fn internal_remove_pending_request(&mut self, request_id: u64) -> Option<Request> {
+        self.pending_request_ids.remove(&request_id);
+        self.pending_requests.remove(&request_id)
+    }
+
+    pub(crate) fn internal_resume_request(&self, request: &Request, outcome: RequestOutcome) {
+        // This will have no effect if the request already timed out
+        env::promise_yield_resume(
+            &request.yield_index.data_id,
+            &serde_json::to_vec(&outcome).unwrap(),
+        );
+    }
+
+    /// Timed out requests stay pending until purged, but can no longer be resumed
+    pub(crate) fn is_request_timed_out(&self, request: &Request) -> bool {
+        request.is_expired_at(env::block_height())
+            || self
+                .request_records
+                .get(&request.request_id)
+                .is_some_and(|record| record.is_completed())
+    }
+
+    pub(crate) fn is_result_expired(&self, result: &FulfilledResult) -> bool {
+        env::block_height() > result.block_height + self.result_retention_blocks
+    }
+
     fn internal_register_worker(
         &mut self,
         codehash: String,
         public_key: PublicKey,
         checksum: String,
+        attestation: Option<WorkerAttestation>,
     ) {
         let worker_id = env::predecessor_account_id();
 
@@ -311,23 +663,22 @@ impl Contract {
                 checksum: checksum.clone(),
                 codehash: codehash.clone(),
                 public_key: public_key.clone(),
+                attested_at: block_timestamp() / 1_000_000_000,
+                attestation,
             },
         );
 
         Event::WorkerRegistered {
-            worker_id: &worker_id,
-            public_key: &public_key,
-            codehash: &codehash,
-            checksum: &checksum,
+            worker_id,
+            public_key,
+            codehash,
+            checksum,
         }
         .emit();
     }
 
     fn assert_approved_codehash(&self, codehash: &String) {
-        require!(
-            self.approved_codehashes.contains(codehash),
-            "Invalid code hash"
-        );
+        require!(self.is_codehash_approved(codehash), "Invalid code hash");
     }
 
     fn require_approved_worker(&self) -> &Worker {
@@ -345,11 +696,14 @@ impl Contract {
 mod tests {
     use super::*;
     use ed25519_dalek::{Signer, SigningKey};
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::mock::MockAction;
+    use near_sdk::serde_json::{json, Value};
+    use near_sdk::test_utils::{accounts, get_created_receipts, get_logs, VMContextBuilder};
     use near_sdk::{testing_env, NearToken};
     use rand::rngs::OsRng;
     use sha2::{Digest, Sha256};
     use sha3::{Digest as KeccakDigest, Keccak256};
+    use tee_rng_sdk::ATTESTATION_FRESHNESS_SECONDS;
 
     const ONE_YOCTO_NEAR: NearToken = NearToken::from_yoctonear(1);
     const NO_DEPOSIT: NearToken = NearToken::from_yoctonear(0);
@@ -370,8 +724,25 @@ mod tests {
         accounts(3)
     }
 
+    fn ft_token_account_id() -> AccountId {
+        accounts(4)
+    }
+
+    fn manager_account_id() -> AccountId {
+        accounts(5)
+    }
+
     fn approved_codehash() -> String {
-        "0x0000000000000000000000000000000000000000000000000000000000000000".to_string()
+        "0000000000000000000000000000000000000000000000000000000000000000".to_string()
+    }
+
+    fn codehash_details() -> Option<CodehashDetails> {
+        Some(CodehashDetails {
+            label: "tee-rng-worker".to_string(),
+            worker_version: "0.1.0".to_string(),
+            source_commit: "e57559c".to_string(),
+            expires_at: None,
+        })
     }
 
     fn set_context(signer_account_id: AccountId, attached_deposit: NearToken) {
@@ -399,17 +770,92 @@ mod tests {
         testing_env!(context);
     }
 
+    fn set_context_with_input(signer_account_id: AccountId, input: Vec<u8>, block_height: u64) {
+        let context = VMContextBuilder::new()
+            .current_account_id(contract_account_id())
+            .predecessor_account_id(signer_account_id.clone())
+            .signer_account_id(signer_account_id)
+            .block_height(block_height)
+            .input(input)
+            .build();
+        testing_env!(context);
+    }
+
+    fn set_context_with_promise_result(promise_result: PromiseResult) {
+        let context = VMContextBuilder::new()
+            .current_account_id(contract_account_id())
+            .predecessor_account_id(contract_account_id())
+            .build();
+        testing_env!(
+            context,
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![promise_result]
+        );
+    }
+
     fn get_contract() -> Contract {
         Contract::new(owner_account_id())
     }
 
+    fn ft_payment() -> Payment {
+        Payment::FungibleToken {
+            token_id: ft_token_account_id(),
+            amount: U128(100),
+        }
+    }
+
+    /// Run the callback of a yielded request of the requester with the outcome it's resumed with
+    fn run_callback(
+        contract: &mut Contract,
+        request_id: u64,
+        payment: Payment,
+        outcome: Result<RequestOutcome, PromiseError>,
+    ) -> Option<String> {
+        let args = (request_id, requester_account_id(), payment);
+        set_context_with_input(contract_account_id(), serde_json::to_vec(&args).unwrap(), 0);
+        match contract.on_received_response(outcome.map(ResumeData::from)) {
+            PromiseOrValue::Value(value) => value,
+            PromiseOrValue::Promise(_) => panic!("Expected a value"),
+        }
+    }
+
+    /// NEAR transferred by the receipts created in the current context
+    fn transferred_amounts() -> Vec<NearToken> {
+        get_created_receipts()
+            .into_iter()
+            .filter_map(|action| match action {
+                MockAction::Transfer { deposit, .. } => Some(deposit),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Arguments of the `ft_transfer` calls created in the current context
+    fn ft_transfers() -> Vec<Value> {
+        get_created_receipts()
+            .into_iter()
+            .filter_map(|action| match action {
+                MockAction::FunctionCallWeight {
+                    method_name, args, ..
+                } if method_name == b"ft_transfer" => Some(serde_json::from_slice(&args).unwrap()),
+                _ => None,
+            })
+            .collect()
+    }
+
     #[test]
     fn test_register_worker() {
         let mut contract = get_contract();
 
         // Approve the codehash
         set_context(owner_account_id(), ONE_YOCTO_NEAR);
-        contract.approve_codehash(approved_codehash());
+        contract.propose_codehash(
+            CodehashAction::Approve,
+            approved_codehash(),
+            codehash_details(),
+        );
 
         // Register the worker
         let quote_hex = "0x1234567890".to_string();
@@ -424,55 +870,972 @@ mod tests {
 
         let worker = contract.get_worker(worker_account_id());
         assert_eq!(worker.unwrap().public_key, env::signer_account_pk());
+        assert!(contract.is_service_available());
+
+        // The attestation is no longer fresh after the freshness period
+        let context = VMContextBuilder::new()
+            .block_timestamp((ATTESTATION_FRESHNESS_SECONDS + 1) * 1_000_000_000)
+            .build();
+        testing_env!(context);
+        assert!(!contract.is_service_available());
     }
 
     #[test]
-    fn test_request() {
+    fn test_get_worker_attestation() {
         let mut contract = get_contract();
+        let attestation = WorkerAttestation {
+            quote_hash: "00".repeat(32),
+            mrtd: "01".repeat(48),
+            rtmr0: "02".repeat(48),
+            rtmr1: "03".repeat(48),
+            rtmr2: "04".repeat(48),
+            rtmr3: "05".repeat(48),
+            tcb_status: "UpToDate".to_string(),
+            fmspc: "20a06f000000".to_string(),
+            registered_at: 0,
+        };
 
-        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
-        contract.request();
+        set_context(worker_account_id(), ONE_YOCTO_NEAR);
+        contract.internal_register_worker(
+            approved_codehash(),
+            env::signer_account_pk(),
+            "checksum".to_string(),
+            Some(attestation.clone()),
+        );
 
-        let requests = contract.get_pending_requests(0, 10);
-        assert_eq!(requests.len(), 1);
-        assert_eq!(requests[0].request_id, 1);
+        assert_eq!(
+            contract.get_worker_attestation(worker_account_id()),
+            Some(&attestation)
+        );
+        assert_eq!(
+            contract.get_worker_attestation(requester_account_id()),
+            None
+        );
     }
 
     #[test]
-    fn test_respond() {
-        let mut contract = get_contract();
+    fn test_verify_registration() {
+        let contract = get_contract();
+        set_context(worker_account_id(), NearToken::from_yoctonear(0));
+
+        // An invalid registration is reported instead of panicking
+        let report = contract.verify_registration(
+            "0x1234567890".to_string(),
+            "{}".to_string(),
+            "{}".to_string(),
+            env::signer_account_pk(),
+        );
+        assert!(!report.quote_verified);
+        assert_eq!(report.tcb_status, None);
+        assert!(!report.report_data_matches);
+        assert!(!report.rtmr3_matches);
+        assert_eq!(report.codehash, None);
+        assert!(!report.codehash_approved);
+        assert_eq!(report.errors[0], "Missing field tcb_info_issuer_chain");
+        assert!(!report.is_valid());
+    }
 
-        // Approve the codehash
-        set_context(owner_account_id(), ONE_YOCTO_NEAR);
-        contract.approve_codehash(approved_codehash());
+    #[test]
+    fn test_two_step_ownership_transfer() {
+        let mut contract = get_contract();
 
-        // Generate a real ed25519 keypair
-        let mut rng = OsRng;
-        let signing_key = SigningKey::generate(&mut rng);
-        let verifying_key = signing_key.verifying_key();
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.propose_owner(manager_account_id());
+        assert_eq!(contract.get_owner_id(), Some(owner_account_id()));
+        assert_eq!(contract.get_proposed_owner_id(), Some(manager_account_id()));
+
+        set_context(manager_account_id(), NO_DEPOSIT);
+        contract.accept_ownership();
+        assert_eq!(contract.get_owner_id(), Some(manager_account_id()));
+        assert_eq!(contract.get_proposed_owner_id(), None);
+        assert_eq!(
+            contract.get_roles(manager_account_id()),
+            vec![Role::Pauser, Role::Treasurer, Role::Upgrader]
+        );
+        // Approvers are only changed by the council
+        assert_eq!(
+            contract.get_roles(owner_account_id()),
+            vec![Role::CodehashManager]
+        );
+    }
 
-        // Create a NEAR public key from the verifying key
-        let public_key_bytes = verifying_key.to_bytes();
-        let public_key =
-            PublicKey::from_parts(near_sdk::CurveType::ED25519, public_key_bytes.to_vec()).unwrap();
+    #[test]
+    #[should_panic(expected = "Missing role Upgrader")]
+    fn test_previous_owner_loses_roles() {
+        let mut contract = get_contract();
 
-        set_context_with_signer(worker_account_id(), public_key.clone(), ONE_YOCTO_NEAR);
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.propose_owner(manager_account_id());
 
-        let quote_hex = "0x1234567890".to_string();
-        let collateral = "0x1234567890".to_string();
-        let checksum = "0x1234567890".to_string();
-        let tcb_info = "0x1234567890".to_string();
+        set_context(manager_account_id(), NO_DEPOSIT);
+        contract.accept_ownership();
 
-        contract.register_worker(quote_hex, collateral, checksum, tcb_info);
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.assert_role(Role::Upgrader);
+    }
 
-        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
-        contract.request();
+    #[test]
+    #[should_panic(expected = "Only the proposed owner can accept the ownership")]
+    fn test_accept_cancelled_ownership_transfer() {
+        let mut contract = get_contract();
 
-        let requests = contract.get_pending_requests(0, 10);
-        assert_eq!(requests.len(), 1);
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.propose_owner(manager_account_id());
+        contract.cancel_ownership_transfer();
 
-        let request = requests[0];
+        set_context(manager_account_id(), NO_DEPOSIT);
+        contract.accept_ownership();
+    }
 
+    #[test]
+    #[should_panic(expected = "Ownership has been renounced")]
+    fn test_renounce_ownership() {
+        let mut contract = get_contract();
+
+        set_context(owner_account_id(), ONE_YOCTO_NEAR);
+        contract.renounce_ownership();
+        assert_eq!(contract.get_owner_id(), None);
+        assert_eq!(
+            contract.get_roles(owner_account_id()),
+            vec![Role::CodehashManager]
+        );
+
+        contract.grant_role(owner_account_id(), Role::Upgrader);
+    }
+
+    #[test]
+    fn test_renounce_ownership_keeps_other_roles() {
+        let mut contract = get_contract();
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.grant_role(manager_account_id(), Role::Pauser);
+        set_context(owner_account_id(), ONE_YOCTO_NEAR);
+        contract.renounce_ownership();
+        assert_eq!(
+            contract.get_roles(owner_account_id()),
+            vec![Role::CodehashManager]
+        );
+        assert_eq!(contract.get_roles(manager_account_id()), vec![Role::Pauser]);
+
+        // The pause switch still works after the ownership is renounced
+        set_context(manager_account_id(), NO_DEPOSIT);
+        contract.pause(PausableMethod::Request);
+        assert!(contract.is_paused(PausableMethod::Request));
+    }
+
+    #[test]
+    fn test_grant_and_revoke_role() {
+        let mut contract = get_contract();
+        assert_eq!(contract.get_roles(owner_account_id()), Role::ALL.to_vec());
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.grant_role(manager_account_id(), Role::Pauser);
+        assert!(contract.has_role(manager_account_id(), Role::Pauser));
+        assert!(!contract.has_role(manager_account_id(), Role::Upgrader));
+        assert_eq!(
+            contract.get_role_members(Role::Pauser),
+            vec![&owner_account_id(), &manager_account_id()]
+        );
+
+        set_context(manager_account_id(), NO_DEPOSIT);
+        contract.pause(PausableMethod::Request);
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.revoke_role(manager_account_id(), Role::Pauser);
+        assert!(contract.get_roles(manager_account_id()).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Approvers are changed by council proposals")]
+    fn test_grant_codehash_manager_role() {
+        let mut contract = get_contract();
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.grant_role(manager_account_id(), Role::CodehashManager);
+    }
+
+    #[test]
+    #[should_panic(expected = "Approvers are changed by council proposals")]
+    fn test_revoke_codehash_manager_role() {
+        let mut contract = get_contract();
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.revoke_role(owner_account_id(), Role::CodehashManager);
+    }
+
+    /// Add the manager to the council, which then needs both approvers to vote
+    fn add_manager_to_council(contract: &mut Contract) {
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.propose_council_action(CouncilAction::AddApprover(manager_account_id()));
+        contract.propose_council_action(CouncilAction::SetApprovalThreshold(2));
+        assert_eq!(
+            contract.get_role_members(Role::CodehashManager),
+            vec![&owner_account_id(), &manager_account_id()]
+        );
+        assert_eq!(contract.get_approval_threshold(), 2);
+    }
+
+    #[test]
+    fn test_council_proposal_threshold() {
+        let mut contract = get_contract();
+        add_manager_to_council(&mut contract);
+
+        let proposal_id =
+            contract.propose_council_action(CouncilAction::AddApprover(worker_account_id()));
+        assert!(!contract.has_role(worker_account_id(), Role::CodehashManager));
+        assert_eq!(
+            contract.get_council_proposal(proposal_id).unwrap().votes,
+            vec![owner_account_id()]
+        );
+
+        set_context(manager_account_id(), NO_DEPOSIT);
+        contract.vote_council_proposal(proposal_id);
+        assert!(contract.has_role(worker_account_id(), Role::CodehashManager));
+        assert!(contract.get_council_proposal(proposal_id).is_none());
+
+        let proposal_id =
+            contract.propose_council_action(CouncilAction::RemoveApprover(owner_account_id()));
+        set_context(worker_account_id(), NO_DEPOSIT);
+        contract.vote_council_proposal(proposal_id);
+        assert_eq!(
+            contract.get_role_members(Role::CodehashManager),
+            vec![&manager_account_id(), &worker_account_id()]
+        );
+        // The owner's other roles are kept
+        assert!(contract.has_role(owner_account_id(), Role::Upgrader));
+    }
+
+    #[test]
+    #[should_panic(expected = "Threshold can't exceed the number of approvers")]
+    fn test_council_threshold_above_approvers() {
+        let mut contract = get_contract();
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.propose_council_action(CouncilAction::SetApprovalThreshold(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Approvers can't drop below the approval threshold")]
+    fn test_council_remove_approver_below_threshold() {
+        let mut contract = get_contract();
+        add_manager_to_council(&mut contract);
+
+        contract.propose_council_action(CouncilAction::RemoveApprover(manager_account_id()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Approvers can't drop below the approval threshold")]
+    fn test_council_action_checked_when_executed() {
+        let mut contract = get_contract();
+        add_manager_to_council(&mut contract);
+        let proposal_id =
+            contract.propose_council_action(CouncilAction::AddApprover(worker_account_id()));
+        set_context(manager_account_id(), NO_DEPOSIT);
+        contract.vote_council_proposal(proposal_id);
+
+        // Both removals are valid with three approvers, but only one of them can take effect
+        let first_id =
+            contract.propose_council_action(CouncilAction::RemoveApprover(worker_account_id()));
+        let second_id =
+            contract.propose_council_action(CouncilAction::RemoveApprover(owner_account_id()));
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.vote_council_proposal(first_id);
+        assert!(!contract.has_role(worker_account_id(), Role::CodehashManager));
+
+        contract.vote_council_proposal(second_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing role CodehashManager")]
+    fn test_propose_codehash_without_role() {
+        let mut contract = get_contract();
+
+        set_context(manager_account_id(), NO_DEPOSIT);
+        contract.propose_codehash(
+            CodehashAction::Approve,
+            approved_codehash(),
+            codehash_details(),
+        );
+    }
+
+    #[test]
+    fn test_codehash_proposal_threshold() {
+        let mut contract = get_contract();
+
+        add_manager_to_council(&mut contract);
+
+        let proposal_id = contract.propose_codehash(
+            CodehashAction::Approve,
+            approved_codehash(),
+            codehash_details(),
+        );
+        assert!(contract.get_codehash(approved_codehash()).is_none());
+        assert_eq!(contract.get_proposal(proposal_id).unwrap().votes.len(), 1);
+
+        set_context(manager_account_id(), NO_DEPOSIT);
+        contract.vote_codehash_proposal(proposal_id);
+        let metadata = contract.get_codehash(approved_codehash()).unwrap();
+        assert_eq!(metadata.label, "tee-rng-worker");
+        assert_eq!(
+            metadata.approved_by,
+            vec![owner_account_id(), manager_account_id()]
+        );
+        assert_eq!(contract.get_approved_codehashes(0, 10).len(), 1);
+        assert!(contract.get_proposal(proposal_id).is_none());
+
+        let proposal_id =
+            contract.propose_codehash(CodehashAction::Revoke, approved_codehash(), None);
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.vote_codehash_proposal(proposal_id);
+        assert!(contract.get_codehash(approved_codehash()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Upgrade delay must be at least 43200 blocks")]
+    fn test_set_upgrade_delay_below_minimum() {
+        let mut contract = get_contract();
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.set_upgrade_delay_blocks(MIN_UPGRADE_DELAY_BLOCKS);
+        contract.set_upgrade_delay_blocks(MIN_UPGRADE_DELAY_BLOCKS - 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Codehash must be 64 lowercase hex characters")]
+    fn test_propose_uppercase_codehash() {
+        let mut contract = get_contract();
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.propose_codehash(CodehashAction::Approve, "AB".repeat(32), codehash_details());
+    }
+
+    #[test]
+    #[should_panic(expected = "Codehash must be 64 lowercase hex characters")]
+    fn test_propose_invalid_codehash() {
+        let mut contract = get_contract();
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.propose_codehash(
+            CodehashAction::Approve,
+            format!("0x{}", approved_codehash()),
+            codehash_details(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Proposal has expired")]
+    fn test_vote_expired_codehash_proposal() {
+        let mut contract = get_contract();
+
+        add_manager_to_council(&mut contract);
+        let proposal_id = contract.propose_codehash(
+            CodehashAction::Approve,
+            approved_codehash(),
+            codehash_details(),
+        );
+
+        let context = VMContextBuilder::new()
+            .current_account_id(contract_account_id())
+            .predecessor_account_id(manager_account_id())
+            .block_height(DEFAULT_PROPOSAL_EXPIRY_BLOCKS + 1)
+            .build();
+        testing_env!(context);
+        contract.vote_codehash_proposal(proposal_id);
+    }
+
+    #[test]
+    fn test_stage_and_deploy_upgrade() {
+        let mut contract = get_contract();
+        let code = vec![1, 2, 3];
+
+        set_context_with_input(owner_account_id(), code.clone(), 0);
+        contract.stage_upgrade();
+        let staged_upgrade = contract.get_staged_upgrade().unwrap();
+        assert_eq!(
+            staged_upgrade.activation_height,
+            DEFAULT_UPGRADE_DELAY_BLOCKS
+        );
+
+        set_context_with_input(owner_account_id(), code, DEFAULT_UPGRADE_DELAY_BLOCKS);
+        contract.deploy_staged_upgrade();
+
+        // Requests can still land before the deployment batch runs
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        env::state_write(&contract);
+        drop(contract);
+
+        // The migration and self-check run on the new code
+        set_context(contract_account_id(), ONE_YOCTO_NEAR);
+        let mut contract = Contract::migrate();
+        contract.check_upgrade(staged_upgrade.code_hash.clone());
+        assert!(contract.get_staged_upgrade().is_none());
+        assert_eq!(contract.get_code_hash(), Some(staged_upgrade.code_hash));
+        assert_eq!(contract.get_previous_code_hash(), None);
+        assert_eq!(contract.get_pending_requests(0, 10).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Worker count changed")]
+    fn test_check_upgrade_with_lost_workers() {
+        let mut contract = get_contract();
+
+        set_context(contract_account_id(), NO_DEPOSIT);
+        write_upgrade_invariants(&UpgradeInvariants {
+            state_version: CURRENT_STATE_VERSION,
+            owner_id: Some(owner_account_id()),
+            worker_count: 1,
+            pending_request_count: 0,
+        });
+        contract.check_upgrade("00".repeat(32));
+    }
+
+    #[test]
+    #[should_panic(expected = "Upgrade invariants not found")]
+    fn test_check_upgrade_without_migration() {
+        let mut contract = get_contract();
+
+        set_context(contract_account_id(), NO_DEPOSIT);
+        contract.check_upgrade("00".repeat(32));
+    }
+
+    #[test]
+    #[should_panic(expected = "Upgrade delay has not passed")]
+    fn test_deploy_staged_upgrade_before_delay() {
+        let mut contract = get_contract();
+        let code = vec![1, 2, 3];
+
+        set_context_with_input(owner_account_id(), code.clone(), 0);
+        contract.stage_upgrade();
+
+        set_context_with_input(owner_account_id(), code, DEFAULT_UPGRADE_DELAY_BLOCKS - 1);
+        contract.deploy_staged_upgrade();
+    }
+
+    #[test]
+    #[should_panic(expected = "No staged upgrade")]
+    fn test_deploy_cancelled_upgrade() {
+        let mut contract = get_contract();
+        let code = vec![1, 2, 3];
+
+        set_context_with_input(owner_account_id(), code.clone(), 0);
+        contract.stage_upgrade();
+        contract.cancel_staged_upgrade();
+
+        set_context_with_input(owner_account_id(), code, DEFAULT_UPGRADE_DELAY_BLOCKS);
+        contract.deploy_staged_upgrade();
+    }
+
+    #[test]
+    fn test_request() {
+        let mut contract = get_contract();
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+
+        let requests = contract.get_pending_requests(0, 10);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].request_id, 1);
+    }
+
+    #[test]
+    fn test_pause_and_unpause_request() {
+        let mut contract = get_contract();
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.pause(PausableMethod::Request);
+        assert!(contract.is_paused(PausableMethod::Request));
+        assert!(!contract.is_paused(PausableMethod::Respond));
+
+        contract.unpause(PausableMethod::Request);
+        assert!(contract.get_paused_methods().is_empty());
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+    }
+
+    #[test]
+    #[should_panic(expected = "Requests are paused")]
+    fn test_request_when_paused() {
+        let mut contract = get_contract();
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.pause(PausableMethod::Request);
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+    }
+
+    #[test]
+    #[should_panic(expected = "Requests are not accepted while responses are paused")]
+    fn test_request_when_respond_paused() {
+        let mut contract = get_contract();
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.pause(PausableMethod::Respond);
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+    }
+
+    #[test]
+    fn test_timed_out_request_refund_when_respond_paused() {
+        let mut contract = get_contract();
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.pause(PausableMethod::Respond);
+
+        run_callback(
+            &mut contract,
+            1,
+            Payment::Near(MIN_REQUEST_DEPOSIT),
+            Err(PromiseError::Failed),
+        );
+        assert_eq!(transferred_amounts(), vec![MIN_REQUEST_DEPOSIT]);
+
+        assert!(contract.get_pending_requests(0, 10).is_empty());
+        assert!(contract.get_pending_requests_since(0, 10).is_empty());
+        assert_eq!(
+            contract.get_request(1).unwrap().status,
+            RequestStatus::TimedOut
+        );
+    }
+
+    #[test]
+    fn test_cancel_request() {
+        let mut contract = get_contract();
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+
+        set_context(requester_account_id(), NO_DEPOSIT);
+        contract.cancel_request(1);
+
+        let requests = contract.get_pending_requests(0, 10);
+        assert_eq!(requests.len(), 0);
+        assert_eq!(
+            contract.get_request(1).unwrap().status,
+            RequestStatus::Cancelled
+        );
+
+        let stats = contract.get_stats();
+        assert_eq!(stats.stats.total_requests, 1);
+        assert_eq!(stats.stats.cancelled, 1);
+        assert_eq!(stats.average_latency_blocks, None);
+    }
+
+    #[test]
+    fn test_get_account_requests() {
+        let mut contract = get_contract();
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        contract.request();
+        set_context(owner_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+
+        let request = contract.get_request(3).unwrap();
+        assert_eq!(request.account_id, owner_account_id());
+        assert_eq!(request.status, RequestStatus::Pending);
+
+        let requests = contract.get_account_requests(requester_account_id(), None, 2);
+        let request_ids: Vec<u64> = requests.iter().map(|r| r.request_id).collect();
+        assert_eq!(request_ids, vec![4, 2]);
+
+        let requests = contract.get_account_requests(requester_account_id(), Some(2), 2);
+        let request_ids: Vec<u64> = requests.iter().map(|r| r.request_id).collect();
+        assert_eq!(request_ids, vec![1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the requester can cancel the request")]
+    fn test_cancel_request_by_other_account() {
+        let mut contract = get_contract();
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+
+        set_context(worker_account_id(), NO_DEPOSIT);
+        contract.cancel_request(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Request has already timed out")]
+    fn test_cancel_expired_request() {
+        let mut contract = get_contract();
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+
+        set_context_with_input(
+            requester_account_id(),
+            vec![],
+            REQUEST_EXPIRATION_BLOCKS + 1,
+        );
+        contract.cancel_request(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Request has already timed out")]
+    fn test_cancel_timed_out_request() {
+        let mut contract = get_contract();
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        run_callback(
+            &mut contract,
+            1,
+            Payment::Near(MIN_REQUEST_DEPOSIT),
+            Err(PromiseError::Failed),
+        );
+        assert_eq!(
+            contract.get_request(1).unwrap().status,
+            RequestStatus::TimedOut
+        );
+
+        set_context(requester_account_id(), NO_DEPOSIT);
+        contract.cancel_request(1);
+    }
+
+    #[test]
+    fn test_fail_requests() {
+        let mut contract = get_contract();
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        contract.request();
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.fail_requests(vec![1, 3]);
+
+        let requests = contract.get_pending_requests(0, 10);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].request_id, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not the owner")]
+    fn test_fail_requests_by_pauser() {
+        let mut contract = get_contract();
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.grant_role(manager_account_id(), Role::Pauser);
+
+        set_context(manager_account_id(), NO_DEPOSIT);
+        contract.fail_requests(vec![1]);
+    }
+
+    #[test]
+    fn test_fail_timed_out_requests() {
+        let mut contract = get_contract();
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        contract.request();
+        run_callback(
+            &mut contract,
+            1,
+            Payment::Near(MIN_REQUEST_DEPOSIT),
+            Err(PromiseError::Failed),
+        );
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.fail_requests(vec![1, 2]);
+
+        // The timed out request is left for purging, but no longer listed for workers
+        assert_eq!(contract.pending_requests.len(), 1);
+        assert!(contract.pending_requests.get(&1).is_some());
+        assert!(contract.get_pending_requests(0, 10).is_empty());
+        assert_eq!(
+            contract.get_request(1).unwrap().status,
+            RequestStatus::TimedOut
+        );
+        assert_eq!(
+            contract.get_request(2).unwrap().status,
+            RequestStatus::Failed
+        );
+    }
+
+    #[test]
+    fn test_get_pending_requests_since() {
+        let mut contract = get_contract();
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        for _ in 0..4 {
+            contract.request();
+        }
+
+        set_context(requester_account_id(), NO_DEPOSIT);
+        contract.cancel_request(2);
+
+        let requests = contract.get_pending_requests_since(0, 2);
+        let request_ids: Vec<u64> = requests.iter().map(|r| r.request_id).collect();
+        assert_eq!(request_ids, vec![1, 3]);
+
+        let requests = contract.get_pending_requests_since(3, 10);
+        let request_ids: Vec<u64> = requests.iter().map(|r| r.request_id).collect();
+        assert_eq!(request_ids, vec![4]);
+    }
+
+    #[test]
+    fn test_purge_expired_requests() {
+        let mut contract = get_contract();
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+
+        set_context(worker_account_id(), NO_DEPOSIT);
+        assert_eq!(contract.purge_expired_requests(10), 0);
+
+        let context = VMContextBuilder::new()
+            .current_account_id(contract_account_id())
+            .predecessor_account_id(worker_account_id())
+            .block_height(REQUEST_EXPIRATION_BLOCKS + 1)
+            .build();
+        testing_env!(context);
+        assert_eq!(contract.purge_expired_requests(10), 1);
+        assert_eq!(transferred_amounts(), vec![PURGE_BOUNTY]);
+
+        let requests = contract.get_pending_requests(0, 10);
+        assert_eq!(requests.len(), 0);
+    }
+
+    #[test]
+    fn test_purge_expired_requests_in_order() {
+        let mut contract = get_contract();
+
+        for block_height in [0, 0, 100] {
+            let context = VMContextBuilder::new()
+                .current_account_id(contract_account_id())
+                .predecessor_account_id(requester_account_id())
+                .attached_deposit(MIN_REQUEST_DEPOSIT)
+                .block_height(block_height)
+                .build();
+            testing_env!(context);
+            contract.request();
+        }
+
+        let context = VMContextBuilder::new()
+            .current_account_id(contract_account_id())
+            .predecessor_account_id(worker_account_id())
+            .block_height(REQUEST_EXPIRATION_BLOCKS + 1)
+            .build();
+        testing_env!(context);
+        // Only `limit` requests are checked
+        assert_eq!(contract.purge_expired_requests(1), 1);
+        // The purge stops at the first request which hasn't expired
+        assert_eq!(contract.purge_expired_requests(10), 1);
+
+        let requests = contract.get_pending_requests_since(0, 10);
+        let request_ids: Vec<u64> = requests.iter().map(|r| r.request_id).collect();
+        assert_eq!(request_ids, vec![3]);
+    }
+
+    #[test]
+    fn test_timed_out_request_refund() {
+        let mut contract = get_contract();
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+
+        let result = run_callback(
+            &mut contract,
+            1,
+            Payment::Near(MIN_REQUEST_DEPOSIT),
+            Err(PromiseError::Failed),
+        );
+        assert_eq!(result, None);
+        assert_eq!(
+            transferred_amounts(),
+            vec![MIN_REQUEST_DEPOSIT.saturating_sub(PURGE_BOUNTY)]
+        );
+
+        // The request is left for purging, which pays out the retained bounty
+        assert_eq!(contract.pending_requests.len(), 1);
+        assert!(contract.get_pending_requests(0, 10).is_empty());
+        assert!(contract.get_pending_requests_since(0, 10).is_empty());
+        assert_eq!(
+            contract.get_request(1).unwrap().status,
+            RequestStatus::TimedOut
+        );
+    }
+
+    #[test]
+    fn test_ft_request() {
+        let mut contract = get_contract();
+
+        set_context(owner_account_id(), ONE_YOCTO_NEAR);
+        contract.set_ft_price(ft_token_account_id(), U128(100));
+
+        set_context(ft_token_account_id(), ONE_YOCTO_NEAR);
+        let unused = contract.ft_on_transfer(
+            requester_account_id(),
+            U128(150),
+            r#"{"request":{}}"#.to_string(),
+        );
+        match unused {
+            PromiseOrValue::Value(unused) => assert_eq!(unused.0, 50),
+            _ => panic!("Expected unused amount"),
+        }
+
+        let requests = contract.get_pending_requests(0, 10);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].request_id, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is not accepted")]
+    fn test_ft_request_unknown_token() {
+        let mut contract = get_contract();
+
+        set_context(ft_token_account_id(), ONE_YOCTO_NEAR);
+        let _ = contract.ft_on_transfer(
+            requester_account_id(),
+            U128(150),
+            r#"{"request":{}}"#.to_string(),
+        );
+    }
+
+    #[test]
+    fn test_ft_fees_collected_on_fulfilment() {
+        let mut contract = get_contract();
+
+        run_callback(
+            &mut contract,
+            1,
+            ft_payment(),
+            Ok(RequestOutcome::Fulfilled(vec![1; 32])),
+        );
+        assert!(ft_transfers().is_empty());
+        run_callback(
+            &mut contract,
+            2,
+            ft_payment(),
+            Ok(RequestOutcome::Fulfilled(vec![2; 32])),
+        );
+
+        assert_eq!(
+            contract.get_ft_fees(),
+            vec![(&ft_token_account_id(), &U128(200))]
+        );
+    }
+
+    #[test]
+    fn test_ft_refund_on_failure() {
+        let mut contract = get_contract();
+        let refund = json!({ "receiver_id": requester_account_id(), "amount": "100" });
+
+        let result = run_callback(
+            &mut contract,
+            1,
+            ft_payment(),
+            Ok(RequestOutcome::Cancelled),
+        );
+        assert_eq!(result, None);
+        assert_eq!(ft_transfers(), vec![refund.clone()]);
+
+        // Timed out requests are refunded in full, there's no bounty in fungible tokens
+        run_callback(&mut contract, 2, ft_payment(), Err(PromiseError::Failed));
+        assert_eq!(ft_transfers(), vec![refund]);
+        assert!(transferred_amounts().is_empty());
+
+        assert!(contract.get_ft_fees().is_empty());
+    }
+
+    #[test]
+    fn test_withdraw_ft_fees() {
+        let mut contract = get_contract();
+        run_callback(
+            &mut contract,
+            1,
+            ft_payment(),
+            Ok(RequestOutcome::Fulfilled(vec![1; 32])),
+        );
+
+        set_context(owner_account_id(), ONE_YOCTO_NEAR);
+        let _ = contract.withdraw_ft_fees(ft_token_account_id(), manager_account_id(), U128(60));
+        assert_eq!(
+            ft_transfers(),
+            vec![json!({ "receiver_id": manager_account_id(), "amount": "60" })]
+        );
+        assert_eq!(
+            contract.get_ft_fees(),
+            vec![(&ft_token_account_id(), &U128(40))]
+        );
+
+        // The fees are kept if the transfer succeeded
+        set_context_with_promise_result(PromiseResult::Successful(vec![]));
+        contract.on_ft_fees_withdrawn(ft_token_account_id(), U128(60));
+        assert_eq!(
+            contract.get_ft_fees(),
+            vec![(&ft_token_account_id(), &U128(40))]
+        );
+
+        // and restored if it failed
+        set_context_with_promise_result(PromiseResult::Failed);
+        contract.on_ft_fees_withdrawn(ft_token_account_id(), U128(60));
+        assert_eq!(
+            contract.get_ft_fees(),
+            vec![(&ft_token_account_id(), &U128(100))]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough fees collected")]
+    fn test_withdraw_more_ft_fees_than_collected() {
+        let mut contract = get_contract();
+        run_callback(
+            &mut contract,
+            1,
+            ft_payment(),
+            Ok(RequestOutcome::Fulfilled(vec![1; 32])),
+        );
+
+        set_context(owner_account_id(), ONE_YOCTO_NEAR);
+        let _ = contract.withdraw_ft_fees(ft_token_account_id(), manager_account_id(), U128(101));
+    }
+
+    fn worker_public_key(signing_key: &SigningKey) -> PublicKey {
+        let public_key_bytes = signing_key.verifying_key().to_bytes();
+        PublicKey::from_parts(near_sdk::CurveType::ED25519, public_key_bytes.to_vec()).unwrap()
+    }
+
+    /// Approve the codehash and register a worker with a fresh ed25519 keypair
+    fn register_worker(contract: &mut Contract) -> SigningKey {
+        set_context(owner_account_id(), ONE_YOCTO_NEAR);
+        contract.propose_codehash(
+            CodehashAction::Approve,
+            approved_codehash(),
+            codehash_details(),
+        );
+
+        let mut rng = OsRng;
+        let signing_key = SigningKey::generate(&mut rng);
+        set_context_with_signer(
+            worker_account_id(),
+            worker_public_key(&signing_key),
+            ONE_YOCTO_NEAR,
+        );
+
+        let quote_hex = "0x1234567890".to_string();
+        let collateral = "0x1234567890".to_string();
+        let checksum = "0x1234567890".to_string();
+        let tcb_info = "0x1234567890".to_string();
+        contract.register_worker(quote_hex, collateral, checksum, tcb_info);
+
+        signing_key
+    }
+
+    /// Generate and sign the response of a worker to a request
+    fn sign_response(signing_key: &SigningKey, request: &Request) -> Response {
         // Generate random number using SHA256(signing_sk + seed)
         let mut hasher = Sha256::new();
         hasher.update(&signing_key.to_bytes()); // signing_sk
@@ -493,18 +1856,419 @@ mod tests {
 
         // Sign the double keccak256 hash with our private key
         let signature = signing_key.sign(&message_hash);
-        let signature_bytes = signature.to_bytes().to_vec();
 
-        let response = Response {
+        Response {
             request_id: request.request_id,
-            random_number: random_number,
-            signature: signature_bytes,
-        };
+            random_number,
+            signature: signature.to_bytes().to_vec(),
+        }
+    }
+
+    /// Respond to a pending request as the registered worker at the given block height
+    fn respond_to_request(
+        contract: &mut Contract,
+        signing_key: &SigningKey,
+        request_id: u64,
+        block_height: u64,
+    ) -> Response {
+        let request = contract.pending_requests.get(&request_id).unwrap();
+        let response = sign_response(signing_key, request);
+
+        let context = VMContextBuilder::new()
+            .current_account_id(contract_account_id())
+            .predecessor_account_id(worker_account_id())
+            .signer_account_id(worker_account_id())
+            .signer_account_pk(worker_public_key(signing_key))
+            .block_height(block_height)
+            .build();
+        testing_env!(context);
+        contract.respond(response.clone());
+
+        response
+    }
+
+    #[test]
+    fn test_respond() {
+        let mut contract = get_contract();
+        let signing_key = register_worker(&mut contract);
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
 
-        set_context_with_signer(worker_account_id(), public_key, NO_DEPOSIT);
-        contract.respond(response);
+        let requests = contract.get_pending_requests(0, 10);
+        assert_eq!(requests.len(), 1);
+
+        respond_to_request(&mut contract, &signing_key, 1, 0);
 
         let requests = contract.get_pending_requests(0, 10);
         assert_eq!(requests.len(), 0);
     }
+
+    #[test]
+    #[should_panic(expected = "Request timed out")]
+    fn test_respond_to_expired_request() {
+        let mut contract = get_contract();
+        let signing_key = register_worker(&mut contract);
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+
+        respond_to_request(
+            &mut contract,
+            &signing_key,
+            1,
+            REQUEST_EXPIRATION_BLOCKS + 1,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Request timed out")]
+    fn test_respond_to_timed_out_request() {
+        let mut contract = get_contract();
+        let signing_key = register_worker(&mut contract);
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        run_callback(
+            &mut contract,
+            1,
+            Payment::Near(MIN_REQUEST_DEPOSIT),
+            Err(PromiseError::Failed),
+        );
+
+        respond_to_request(&mut contract, &signing_key, 1, 0);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut contract = get_contract();
+        let signing_key = register_worker(&mut contract);
+        assert_eq!(contract.get_stats().average_latency_blocks, None);
+        assert!(contract.get_worker_stats(worker_account_id()).is_none());
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        for _ in 0..5 {
+            contract.request();
+        }
+        respond_to_request(&mut contract, &signing_key, 1, 10);
+        respond_to_request(&mut contract, &signing_key, 2, 20);
+
+        set_context(requester_account_id(), NO_DEPOSIT);
+        contract.cancel_request(3);
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.fail_requests(vec![4]);
+        run_callback(
+            &mut contract,
+            5,
+            Payment::Near(MIN_REQUEST_DEPOSIT),
+            Err(PromiseError::Failed),
+        );
+
+        let stats = contract.get_stats();
+        assert_eq!(stats.stats.total_requests, 5);
+        assert_eq!(stats.stats.fulfilled, 2);
+        assert_eq!(stats.stats.cancelled, 1);
+        assert_eq!(stats.stats.failed, 1);
+        assert_eq!(stats.stats.timed_out, 1);
+        assert_eq!(stats.stats.total_latency_blocks, 30);
+        assert_eq!(stats.average_latency_blocks, Some(15));
+
+        let worker_stats = contract.get_worker_stats(worker_account_id()).unwrap();
+        assert_eq!(worker_stats.stats.responses, 2);
+        assert_eq!(worker_stats.stats.last_response_height, 20);
+        assert_eq!(worker_stats.average_latency_blocks, Some(15));
+    }
+
+    #[test]
+    fn test_is_service_available() {
+        let mut contract = get_contract();
+        assert!(!contract.is_service_available());
+
+        register_worker(&mut contract);
+        assert!(contract.is_service_available());
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.pause(PausableMethod::Respond);
+        assert!(!contract.is_service_available());
+        contract.unpause(PausableMethod::Respond);
+        assert!(contract.is_service_available());
+
+        // Workers only count while their codehash is approved
+        contract.propose_codehash(CodehashAction::Revoke, approved_codehash(), None);
+        assert!(!contract.is_service_available());
+    }
+
+    #[test]
+    fn test_get_fulfilled_request() {
+        let mut contract = get_contract();
+        let signing_key = register_worker(&mut contract);
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        assert_eq!(
+            contract.get_request(1).unwrap().status,
+            RequestStatus::Pending
+        );
+
+        respond_to_request(&mut contract, &signing_key, 1, 5);
+
+        let request = contract.get_request(1).unwrap();
+        assert_eq!(request.status, RequestStatus::Fulfilled);
+        assert_eq!(request.worker_id, Some(worker_account_id()));
+        assert_eq!(request.completed_at, Some(5));
+    }
+
+    #[test]
+    fn test_get_recent_fulfilled_requests() {
+        let mut contract = get_contract();
+        let signing_key = register_worker(&mut contract);
+
+        // Fulfil more requests than the ring buffer holds
+        let count = RECENT_FULFILLED_CAPACITY + 5;
+        for request_id in 1..=count {
+            set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+            contract.request();
+            respond_to_request(&mut contract, &signing_key, request_id, 0);
+        }
+
+        let requests = contract.get_recent_fulfilled_requests(3);
+        let request_ids: Vec<u64> = requests.iter().map(|r| r.request_id).collect();
+        assert_eq!(request_ids, vec![count, count - 1, count - 2]);
+
+        let requests = contract.get_recent_fulfilled_requests(200);
+        assert_eq!(requests.len() as u64, RECENT_FULFILLED_CAPACITY);
+        assert_eq!(requests[0].request_id, count);
+        assert_eq!(
+            requests.last().unwrap().request_id,
+            count - RECENT_FULFILLED_CAPACITY + 1
+        );
+    }
+
+    #[test]
+    fn test_purge_request_history() {
+        let mut contract = get_contract();
+        let signing_key = register_worker(&mut contract);
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        for _ in 0..4 {
+            contract.request();
+        }
+        let deposit = Payment::Near(MIN_REQUEST_DEPOSIT);
+
+        // Fulfilled and cancelled requests retain the bounty for purging their records
+        let response = respond_to_request(&mut contract, &signing_key, 1, 0);
+        run_callback(
+            &mut contract,
+            1,
+            deposit.clone(),
+            Ok(RequestOutcome::Fulfilled(response.random_number)),
+        );
+        assert_eq!(
+            transferred_amounts(),
+            vec![MIN_REQUEST_DEPOSIT.saturating_sub(PURGE_BOUNTY)]
+        );
+
+        set_context(requester_account_id(), NO_DEPOSIT);
+        contract.cancel_request(2);
+        run_callback(
+            &mut contract,
+            2,
+            deposit.clone(),
+            Ok(RequestOutcome::Cancelled),
+        );
+        assert_eq!(
+            transferred_amounts(),
+            vec![MIN_REQUEST_DEPOSIT.saturating_sub(PURGE_BOUNTY)]
+        );
+
+        // Failed requests are refunded in full
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.fail_requests(vec![3]);
+        run_callback(&mut contract, 3, deposit, Ok(RequestOutcome::Failed));
+        assert_eq!(transferred_amounts(), vec![MIN_REQUEST_DEPOSIT]);
+
+        set_context_with_input(worker_account_id(), vec![], DEFAULT_RESULT_RETENTION_BLOCKS);
+        assert_eq!(contract.purge_request_history(10), 0);
+
+        set_context_with_input(
+            worker_account_id(),
+            vec![],
+            DEFAULT_RESULT_RETENTION_BLOCKS + 1,
+        );
+        assert_eq!(contract.purge_request_history(10), 3);
+        assert_eq!(transferred_amounts(), vec![PURGE_BOUNTY.saturating_mul(2)]);
+        assert!(get_logs()
+            .iter()
+            .any(|log| log.contains("request_history_purged")));
+
+        for request_id in 1..=3 {
+            assert!(contract.get_request(request_id).is_none());
+        }
+        assert!(contract.get_result(1).is_none());
+        // Pending requests are kept
+        let requests = contract.get_account_requests(requester_account_id(), None, 10);
+        let request_ids: Vec<u64> = requests.iter().map(|r| r.request_id).collect();
+        assert_eq!(request_ids, vec![4]);
+    }
+
+    #[test]
+    fn test_verify_response() {
+        let mut contract = get_contract();
+        let signing_key = register_worker(&mut contract);
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        let random_seed = contract.get_request(1).unwrap().random_seed;
+        let response = respond_to_request(&mut contract, &signing_key, 1, 0);
+
+        let verification = contract.verify_response(
+            1,
+            random_seed.clone(),
+            response.random_number.clone(),
+            response.signature.clone(),
+            worker_account_id(),
+        );
+        assert!(verification.signature_valid);
+        assert!(verification.codehash_approved);
+
+        let verification = contract.verify_response(
+            1,
+            random_seed.clone(),
+            vec![0; 32],
+            response.signature.clone(),
+            worker_account_id(),
+        );
+        assert!(!verification.signature_valid);
+
+        let verification = contract.verify_response(
+            1,
+            random_seed.clone(),
+            response.random_number.clone(),
+            response.signature.clone(),
+            requester_account_id(),
+        );
+        assert!(!verification.signature_valid);
+        assert!(!verification.codehash_approved);
+
+        // Responses stay verifiable after the worker's codehash is revoked
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.propose_codehash(CodehashAction::Revoke, approved_codehash(), None);
+        let verification = contract.verify_response(
+            1,
+            random_seed,
+            response.random_number,
+            response.signature,
+            worker_account_id(),
+        );
+        assert!(verification.signature_valid);
+        assert!(!verification.codehash_approved);
+    }
+
+    #[test]
+    fn test_get_result() {
+        let mut contract = get_contract();
+        let signing_key = register_worker(&mut contract);
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        let response = respond_to_request(&mut contract, &signing_key, 1, 5);
+
+        let result = contract.get_result(1).unwrap();
+        assert_eq!(result.account_id, requester_account_id());
+        assert_eq!(result.random_number, response.random_number);
+        assert_eq!(result.block_height, 5);
+    }
+
+    #[test]
+    fn test_claim_result() {
+        let mut contract = get_contract();
+        let signing_key = register_worker(&mut contract);
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        let response = respond_to_request(&mut contract, &signing_key, 1, 0);
+
+        set_context(requester_account_id(), NO_DEPOSIT);
+        assert_eq!(contract.claim_result(1), encode(&response.random_number));
+        assert!(contract.get_result(1).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the requester can claim the result")]
+    fn test_claim_result_by_other_account() {
+        let mut contract = get_contract();
+        let signing_key = register_worker(&mut contract);
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        respond_to_request(&mut contract, &signing_key, 1, 0);
+
+        set_context(owner_account_id(), NO_DEPOSIT);
+        contract.claim_result(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Result has expired")]
+    fn test_claim_expired_result() {
+        let mut contract = get_contract();
+        let signing_key = register_worker(&mut contract);
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        respond_to_request(&mut contract, &signing_key, 1, 0);
+
+        set_context_with_input(
+            requester_account_id(),
+            vec![],
+            DEFAULT_RESULT_RETENTION_BLOCKS + 1,
+        );
+        contract.claim_result(1);
+    }
+
+    #[test]
+    fn test_purge_expired_results() {
+        let mut contract = get_contract();
+        let signing_key = register_worker(&mut contract);
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        contract.request();
+        respond_to_request(&mut contract, &signing_key, 1, 0);
+        respond_to_request(&mut contract, &signing_key, 2, 100);
+
+        set_context_with_input(worker_account_id(), vec![], DEFAULT_RESULT_RETENTION_BLOCKS);
+        assert_eq!(contract.purge_expired_results(10), 0);
+
+        set_context_with_input(
+            worker_account_id(),
+            vec![],
+            DEFAULT_RESULT_RETENTION_BLOCKS + 1,
+        );
+        assert_eq!(contract.purge_expired_results(10), 1);
+        assert!(contract.get_result(1).is_none());
+        assert!(contract.get_result(2).is_some());
+    }
+
+    #[test]
+    fn test_purge_expired_results_limit() {
+        let mut contract = get_contract();
+        let signing_key = register_worker(&mut contract);
+
+        set_context(requester_account_id(), MIN_REQUEST_DEPOSIT);
+        contract.request();
+        contract.request();
+        respond_to_request(&mut contract, &signing_key, 1, 100);
+        respond_to_request(&mut contract, &signing_key, 2, 0);
+
+        // Only the first stored result is checked, which hasn't expired yet
+        set_context_with_input(
+            worker_account_id(),
+            vec![],
+            DEFAULT_RESULT_RETENTION_BLOCKS + 1,
+        );
+        assert_eq!(contract.purge_expired_results(1), 0);
+        assert_eq!(contract.purge_expired_results(2), 1);
+        assert!(contract.get_result(1).is_some());
+        assert!(contract.get_result(2).is_none());
+    }
 }
@@ -0,0 +1,329 @@
+use crate::*;
+use near_sdk::borsh::{self, BorshDeserialize};
+use near_sdk::store::IterableSet;
+
+// Storage key of the state version. It's kept outside of the contract state, so the version
+// is known before reading the state. States without the key were written before versioning.
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+
+/// Version of the state layout. Version 1 is the first versioned layout, and it covers every
+/// layout change made before it was deployed. Later changes of the stored layout have to bump it.
+pub const CURRENT_STATE_VERSION: u32 = 1;
+
+/// Worker as stored in state version 0
+#[near(serializers = [borsh])]
+pub struct WorkerV0 {
+    pub checksum: String,
+    pub codehash: String,
+    pub public_key: PublicKey,
+}
+
+/// Request as stored in state version 0
+#[near(serializers = [borsh])]
+pub struct RequestV0 {
+    pub request_id: u64,
+    pub random_seed: Vec<u8>,
+    pub yield_index: YieldIndex,
+}
+
+/// Contract state version 0, before the state was versioned
+#[near(serializers = [borsh])]
+pub struct ContractV0 {
+    pub owner_id: AccountId,
+    pub approved_codehashes: IterableSet<String>,
+    pub worker_by_account_id: IterableMap<AccountId, WorkerV0>,
+    pub pending_requests: IterableMap<u64, RequestV0>,
+    pub last_request_id: u64,
+}
+
+pub enum VersionedContract {
+    V0(ContractV0),
+    V1(Contract),
+}
+
+impl VersionedContract {
+    pub fn read() -> Self {
+        match read_state_version() {
+            0 => Self::V0(env::state_read().expect("Failed to read contract state")),
+            1 => Self::V1(env::state_read().expect("Failed to read contract state")),
+            version => env::panic_str(&format!("Unsupported state version {}", version)),
+        }
+    }
+
+    /// State which has to be preserved by the migration
+    pub fn invariants(&self) -> UpgradeInvariants {
+        match self {
+            Self::V0(state) => UpgradeInvariants {
+                state_version: 0,
+                owner_id: Some(state.owner_id.clone()),
+                worker_count: state.worker_by_account_id.len(),
+                // Pending requests of version 0 are failed by the migration
+                pending_request_count: 0,
+            },
+            Self::V1(state) => UpgradeInvariants {
+                state_version: 1,
+                owner_id: state.owner_id.clone(),
+                worker_count: state.worker_by_account_id.len(),
+                pending_request_count: state.pending_requests.len(),
+            },
+        }
+    }
+
+    /// Migrate the state to the current version
+    pub fn migrate(self) -> Contract {
+        match self {
+            Self::V0(state) => migrate_v0(state),
+            Self::V1(state) => state,
+        }
+    }
+}
+
+pub fn read_state_version() -> u32 {
+    env::storage_read(STATE_VERSION_KEY)
+        .map(|version| u32::try_from_slice(&version).expect("Invalid state version"))
+        .unwrap_or(0)
+}
+
+pub fn write_state_version() {
+    env::storage_write(
+        STATE_VERSION_KEY,
+        &borsh::to_vec(&CURRENT_STATE_VERSION).unwrap(),
+    );
+}
+
+impl From<WorkerV0> for Worker {
+    fn from(worker: WorkerV0) -> Self {
+        Self {
+            checksum: worker.checksum,
+            codehash: worker.codehash,
+            public_key: worker.public_key,
+            // Unknown for workers registered before versioning, so they have to register again
+            // to count as freshly attested
+            attested_at: 0,
+            attestation: None,
+        }
+    }
+}
+
+fn migrate_v0(mut state: ContractV0) -> Contract {
+    // Drain the collections of the old state first, as some of them share the storage prefix
+    // with the new collections
+    let owner_id = state.owner_id.clone();
+    let last_request_id = state.last_request_id;
+    let codehashes: Vec<String> = state.approved_codehashes.drain().collect();
+    let workers: Vec<(AccountId, WorkerV0)> = state.worker_by_account_id.drain().collect();
+    let requests: Vec<(u64, RequestV0)> = state.pending_requests.drain().collect();
+    state.approved_codehashes.flush();
+    state.worker_by_account_id.flush();
+    state.pending_requests.flush();
+    drop(state);
+
+    // Requests of version 0 don't keep the requester and deposit, so they can't be carried
+    // over. They are failed instead, and the callback refunds their deposit in full.
+    for (_, request) in requests {
+        env::promise_yield_resume(
+            &request.yield_index.data_id,
+            &serde_json::to_vec(&RequestOutcome::Failed).unwrap(),
+        );
+    }
+
+    let mut contract = Contract::with_owner(owner_id.clone());
+    contract.last_request_id = last_request_id;
+
+    for codehash in codehashes {
+        contract.approved_codehashes.insert(
+            codehash,
+            CodehashMetadata {
+                label: "legacy".to_string(),
+                worker_version: String::new(),
+                source_commit: String::new(),
+                approved_at: env::block_timestamp().into(),
+                approved_by: vec![owner_id.clone()],
+                expires_at: None,
+            },
+        );
+    }
+    for (account_id, worker) in workers {
+        contract
+            .worker_by_account_id
+            .insert(account_id, worker.into());
+    }
+
+    contract
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{accounts, get_created_receipts, get_logs, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    fn codehash() -> String {
+        "a27359cd0d747ae62300649e959c02707d70a9fb0900a9771d1f6f9311e89c3f".to_string()
+    }
+
+    fn set_context() {
+        let context = VMContextBuilder::new()
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build();
+        testing_env!(context);
+    }
+
+    #[test]
+    fn test_migrate_from_v0() {
+        set_context();
+
+        let mut state = ContractV0 {
+            owner_id: accounts(1),
+            approved_codehashes: IterableSet::new(Prefix::ApprovedCodeHashes),
+            worker_by_account_id: IterableMap::new(Prefix::WorkerByAccountId),
+            pending_requests: IterableMap::new(Prefix::PendingRequests),
+            last_request_id: 5,
+        };
+        state.approved_codehashes.insert(codehash());
+        state.worker_by_account_id.insert(
+            accounts(2),
+            WorkerV0 {
+                checksum: "checksum".to_string(),
+                codehash: codehash(),
+                public_key: env::signer_account_pk(),
+            },
+        );
+        state.approved_codehashes.flush();
+        state.worker_by_account_id.flush();
+        env::state_write(&state);
+
+        let contract = Contract::migrate();
+        assert_eq!(contract.get_state_version(), CURRENT_STATE_VERSION);
+        assert_eq!(contract.get_owner_id(), Some(accounts(1)));
+        assert_eq!(contract.last_request_id, 5);
+        // The state is migrated, not initialized
+        let logs = get_logs();
+        assert_eq!(logs.len(), 1);
+        assert!(logs[0].contains("state_migrated"));
+        assert!(contract.is_codehash_approved(&codehash()));
+        assert_eq!(contract.get_worker_len(), 1);
+        assert_eq!(
+            contract.get_worker(accounts(2)).unwrap().codehash,
+            codehash()
+        );
+    }
+
+    /// Yield a request as state version 0 did and return it as stored in that version
+    fn yield_request_v0(request_id: u64, account_id: &AccountId, deposit: NearToken) -> RequestV0 {
+        env::promise_yield_create(
+            "on_received_response",
+            &serde_json::to_vec(&(account_id, &deposit)).unwrap(),
+            ON_RECEIVED_RESPONSE_CALL_GAS,
+            GasWeight(0),
+            DATA_ID_REGISTER,
+        );
+        let data_id: CryptoHash = env::read_register(DATA_ID_REGISTER)
+            .unwrap()
+            .try_into()
+            .unwrap();
+
+        RequestV0 {
+            request_id,
+            random_seed: env::random_seed(),
+            yield_index: YieldIndex { data_id },
+        }
+    }
+
+    fn run_callback_v0(
+        contract: &mut Contract,
+        account_id: &AccountId,
+        deposit: NearToken,
+        resp: Result<ResumeData, PromiseError>,
+    ) -> Option<String> {
+        let context = VMContextBuilder::new()
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(0))
+            .input(serde_json::to_vec(&(account_id, &deposit)).unwrap())
+            .build();
+        testing_env!(context);
+
+        match contract.on_received_response(resp) {
+            PromiseOrValue::Value(value) => value,
+            PromiseOrValue::Promise(_) => panic!("Expected a value"),
+        }
+    }
+
+    fn transferred_amounts() -> Vec<NearToken> {
+        get_created_receipts()
+            .into_iter()
+            .filter_map(|action| match action {
+                MockAction::Transfer { deposit, .. } => Some(deposit),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_migrate_from_v0_with_pending_request() {
+        set_context();
+
+        let mut state = ContractV0 {
+            owner_id: accounts(1),
+            approved_codehashes: IterableSet::new(Prefix::ApprovedCodeHashes),
+            worker_by_account_id: IterableMap::new(Prefix::WorkerByAccountId),
+            pending_requests: IterableMap::new(Prefix::PendingRequests),
+            last_request_id: 2,
+        };
+        state
+            .pending_requests
+            .insert(2, yield_request_v0(2, &accounts(3), MIN_REQUEST_DEPOSIT));
+        state.pending_requests.flush();
+        env::state_write(&state);
+
+        let mut contract = Contract::migrate();
+        assert_eq!(contract.get_state_version(), CURRENT_STATE_VERSION);
+        assert_eq!(contract.last_request_id, 2);
+        assert!(contract.get_pending_requests(0, 10).is_empty());
+
+        // The request is resumed as failed and refunded in full by the legacy callback
+        let resume_data = serde_json::to_vec(&RequestOutcome::Failed).unwrap();
+        let resp = serde_json::from_slice(&resume_data).unwrap();
+        let result = run_callback_v0(&mut contract, &accounts(3), MIN_REQUEST_DEPOSIT, Ok(resp));
+        assert_eq!(result, None);
+        assert_eq!(transferred_amounts(), vec![MIN_REQUEST_DEPOSIT]);
+    }
+
+    #[test]
+    fn test_callback_of_v0_request() {
+        set_context();
+        let mut contract = Contract::new(accounts(1));
+
+        // Requests answered before the migration were resumed with the random number only
+        let random_number = vec![7; 32];
+        let resume_data = serde_json::to_vec(&random_number).unwrap();
+        let resp = serde_json::from_slice(&resume_data).unwrap();
+        let result = run_callback_v0(&mut contract, &accounts(3), MIN_REQUEST_DEPOSIT, Ok(resp));
+        assert_eq!(result, Some(encode(&random_number)));
+        assert_eq!(transferred_amounts(), vec![MIN_REQUEST_DEPOSIT]);
+
+        let result = run_callback_v0(
+            &mut contract,
+            &accounts(3),
+            MIN_REQUEST_DEPOSIT,
+            Err(PromiseError::Failed),
+        );
+        assert_eq!(result, None);
+        assert_eq!(transferred_amounts(), vec![MIN_REQUEST_DEPOSIT]);
+    }
+
+    #[test]
+    fn test_migrate_current_version() {
+        set_context();
+
+        let contract = Contract::new(accounts(1));
+        env::state_write(&contract);
+
+        let contract = Contract::migrate();
+        assert_eq!(contract.get_state_version(), CURRENT_STATE_VERSION);
+        assert_eq!(contract.get_owner_id(), Some(accounts(1)));
+    }
+}
@@ -0,0 +1,34 @@
+use crate::*;
+
+#[near]
+impl Contract {
+    pub fn pause(&mut self, method: PausableMethod) {
+        self.assert_role(Role::Pauser);
+        if !self.paused_methods.contains(&method) {
+            self.paused_methods.push(method);
+
+            Event::Paused { method }.emit();
+        }
+    }
+
+    pub fn unpause(&mut self, method: PausableMethod) {
+        self.assert_role(Role::Pauser);
+        if self.paused_methods.contains(&method) {
+            self.paused_methods.retain(|m| *m != method);
+
+            Event::Unpaused { method }.emit();
+        }
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_not_paused(&self, method: PausableMethod) {
+        if self.is_paused(method) {
+            env::panic_str(match method {
+                PausableMethod::Request => "Requests are paused",
+                PausableMethod::Respond => "Responses are paused",
+                PausableMethod::RegisterWorker => "Worker registration is paused",
+            });
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use crate::*;
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct CodehashProposal {
+    pub proposal_id: u64,
+    pub action: CodehashAction,
+    pub codehash: String,
+    /// Required when approving a codehash
+    pub details: Option<CodehashDetails>,
+    pub proposer: AccountId,
+    pub votes: Vec<AccountId>,
+    pub block_height: u64,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct CouncilProposal {
+    pub proposal_id: u64,
+    pub action: CouncilAction,
+    pub proposer: AccountId,
+    pub votes: Vec<AccountId>,
+    pub block_height: u64,
+}
+
+#[near]
+impl Contract {
+    /// Propose to approve or revoke a codehash. The proposer's vote is counted right away.
+    pub fn propose_codehash(
+        &mut self,
+        action: CodehashAction,
+        codehash: String,
+        details: Option<CodehashDetails>,
+    ) -> u64 {
+        self.assert_role(Role::CodehashManager);
+        assert_valid_codehash(&codehash);
+        if action == CodehashAction::Approve {
+            require!(details.is_some(), "Codehash details are required");
+        }
+        let proposer = env::predecessor_account_id();
+
+        let proposal_id = self.next_proposal_id();
+        self.codehash_proposals.insert(
+            proposal_id,
+            CodehashProposal {
+                proposal_id,
+                action,
+                codehash: codehash.clone(),
+                details,
+                proposer: proposer.clone(),
+                votes: vec![],
+                block_height: env::block_height(),
+            },
+        );
+
+        Event::CodehashProposed {
+            proposal_id,
+            proposer: proposer.clone(),
+            action,
+            codehash: codehash.clone(),
+        }
+        .emit();
+
+        self.internal_vote(proposal_id, proposer);
+        proposal_id
+    }
+
+    /// Vote for a proposal, which takes effect once it has enough votes
+    pub fn vote_codehash_proposal(&mut self, proposal_id: u64) {
+        self.assert_role(Role::CodehashManager);
+        self.internal_vote(proposal_id, env::predecessor_account_id());
+    }
+
+    /// Propose to change the approvers or the approval threshold. The proposer's vote is counted
+    /// right away.
+    pub fn propose_council_action(&mut self, action: CouncilAction) -> u64 {
+        self.assert_role(Role::CodehashManager);
+        self.assert_valid_council_action(&action);
+        let proposer = env::predecessor_account_id();
+
+        let proposal_id = self.next_proposal_id();
+        self.council_proposals.insert(
+            proposal_id,
+            CouncilProposal {
+                proposal_id,
+                action: action.clone(),
+                proposer: proposer.clone(),
+                votes: vec![],
+                block_height: env::block_height(),
+            },
+        );
+
+        Event::CouncilProposed {
+            proposal_id,
+            proposer: proposer.clone(),
+            action,
+        }
+        .emit();
+
+        self.internal_vote_council(proposal_id, proposer);
+        proposal_id
+    }
+
+    /// Vote for a council proposal, which takes effect once it has enough votes
+    pub fn vote_council_proposal(&mut self, proposal_id: u64) {
+        self.assert_role(Role::CodehashManager);
+        self.internal_vote_council(proposal_id, env::predecessor_account_id());
+    }
+}
+
+impl Contract {
+    fn next_proposal_id(&mut self) -> u64 {
+        self.last_proposal_id += 1;
+        self.last_proposal_id
+    }
+
+    fn approver_count(&self) -> u32 {
+        self.get_role_members(Role::CodehashManager).len() as u32
+    }
+
+    /// Only count votes of approvers who still hold the role
+    fn count_valid_votes(&self, votes: &[AccountId]) -> u32 {
+        votes
+            .iter()
+            .filter(|account_id| self.has_role((*account_id).clone(), Role::CodehashManager))
+            .count() as u32
+    }
+
+    fn assert_not_expired(&self, block_height: u64) {
+        require!(
+            env::block_height() <= block_height + self.proposal_expiry_blocks,
+            "Proposal has expired"
+        );
+    }
+
+    fn internal_vote(&mut self, proposal_id: u64, approver: AccountId) {
+        let block_height = self
+            .codehash_proposals
+            .get(&proposal_id)
+            .expect("Proposal not found")
+            .block_height;
+        self.assert_not_expired(block_height);
+        let proposal = self.codehash_proposals.get_mut(&proposal_id).unwrap();
+        require!(!proposal.votes.contains(&approver), "Already voted");
+        proposal.votes.push(approver.clone());
+        let votes = proposal.votes.clone();
+
+        Event::CodehashVoted {
+            proposal_id,
+            approver,
+        }
+        .emit();
+
+        if self.count_valid_votes(&votes) >= self.approval_threshold {
+            let proposal = self.codehash_proposals.remove(&proposal_id).unwrap();
+            self.internal_execute_proposal(proposal);
+        }
+    }
+
+    fn internal_vote_council(&mut self, proposal_id: u64, approver: AccountId) {
+        let block_height = self
+            .council_proposals
+            .get(&proposal_id)
+            .expect("Proposal not found")
+            .block_height;
+        self.assert_not_expired(block_height);
+        let proposal = self.council_proposals.get_mut(&proposal_id).unwrap();
+        require!(!proposal.votes.contains(&approver), "Already voted");
+        proposal.votes.push(approver.clone());
+        let votes = proposal.votes.clone();
+
+        Event::CouncilVoted {
+            proposal_id,
+            approver,
+        }
+        .emit();
+
+        if self.count_valid_votes(&votes) >= self.approval_threshold {
+            let proposal = self.council_proposals.remove(&proposal_id).unwrap();
+            self.internal_execute_council_proposal(proposal);
+        }
+    }
+
+    /// The approvers must always be able to reach the threshold, so the council can't lock
+    /// itself out
+    fn assert_valid_council_action(&self, action: &CouncilAction) {
+        let approver_count = self.approver_count();
+        match action {
+            CouncilAction::AddApprover(_) => {}
+            CouncilAction::RemoveApprover(account_id) => {
+                if self.has_role(account_id.clone(), Role::CodehashManager) {
+                    require!(
+                        approver_count > self.approval_threshold,
+                        "Approvers can't drop below the approval threshold"
+                    );
+                }
+            }
+            CouncilAction::SetApprovalThreshold(threshold) => {
+                require!(*threshold > 0, "Threshold must be positive");
+                require!(
+                    *threshold <= approver_count,
+                    "Threshold can't exceed the number of approvers"
+                );
+            }
+        }
+    }
+
+    fn internal_execute_council_proposal(&mut self, proposal: CouncilProposal) {
+        // The approvers or the threshold may have changed since the proposal was made
+        self.assert_valid_council_action(&proposal.action);
+        match proposal.action {
+            CouncilAction::AddApprover(account_id) => {
+                self.internal_grant_role(account_id, Role::CodehashManager);
+            }
+            CouncilAction::RemoveApprover(account_id) => {
+                self.internal_revoke_role(account_id, Role::CodehashManager);
+            }
+            CouncilAction::SetApprovalThreshold(threshold) => {
+                self.approval_threshold = threshold;
+
+                Event::ApprovalThresholdUpdated { threshold }.emit();
+            }
+        }
+    }
+
+    fn internal_execute_proposal(&mut self, proposal: CodehashProposal) {
+        match proposal.action {
+            CodehashAction::Approve => {
+                let details = proposal.details.unwrap();
+                self.approved_codehashes.insert(
+                    proposal.codehash.clone(),
+                    CodehashMetadata {
+                        label: details.label,
+                        worker_version: details.worker_version,
+                        source_commit: details.source_commit,
+                        approved_at: env::block_timestamp().into(),
+                        approved_by: proposal.votes,
+                        expires_at: details.expires_at,
+                    },
+                );
+
+                Event::CodehashApproved {
+                    codehash: proposal.codehash,
+                }
+                .emit();
+            }
+            CodehashAction::Revoke => {
+                self.approved_codehashes.remove(&proposal.codehash);
+
+                Event::CodehashRevoked {
+                    codehash: proposal.codehash,
+                }
+                .emit();
+            }
+        }
+    }
+}
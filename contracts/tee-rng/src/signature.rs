@@ -0,0 +1,17 @@
+use crate::*;
+pub use tee_rng_verifier::{ed25519_public_key_bytes, response_message_hash};
+
+/// Verify the response is signed by the given worker's public key
+pub fn verify_response_signature(
+    public_key: &[u8; 32],
+    request_id: u64,
+    random_seed: &[u8],
+    random_number: &[u8],
+    signature: &[u8],
+) -> bool {
+    let Ok(signature) = <&[u8; 64]>::try_from(signature) else {
+        return false;
+    };
+    let message_hash = response_message_hash(request_id, random_seed, random_number);
+    env::ed25519_verify(signature, &message_hash, public_key)
+}
@@ -0,0 +1,25 @@
+use crate::*;
+
+impl Contract {
+    /// Count a new request, or the outcome of a completed one
+    pub(crate) fn internal_record_stats(&mut self, record: &RequestRecord) {
+        match record.status {
+            RequestStatus::Pending => self.stats.total_requests += 1,
+            RequestStatus::Fulfilled => {
+                let latency = record.completed_at.unwrap_or_default() - record.block_height;
+                self.stats.fulfilled += 1;
+                self.stats.total_latency_blocks += latency;
+
+                if let Some(worker_id) = record.worker_id.clone() {
+                    let worker_stats = self.worker_stats.entry(worker_id).or_default();
+                    worker_stats.responses += 1;
+                    worker_stats.total_latency_blocks += latency;
+                    worker_stats.last_response_height = env::block_height();
+                }
+            }
+            RequestStatus::TimedOut => self.stats.timed_out += 1,
+            RequestStatus::Cancelled => self.stats.cancelled += 1,
+            RequestStatus::Failed => self.stats.failed += 1,
+        }
+    }
+}
@@ -1,10 +1,42 @@
-use crate::{Contract, ContractExt};
+use crate::events::{EmitEvent, Event};
+use crate::migration::{
+    read_state_version, write_state_version, VersionedContract, CURRENT_STATE_VERSION,
+};
+use crate::{Contract, ContractExt, Role};
 
+use hex::encode;
+use near_sdk::borsh::{self, BorshDeserialize};
+use near_sdk::serde_json::{self, json};
 use near_sdk::{
-    assert_one_yocto, env, near_bindgen, AccountId, Gas, GasWeight, NearToken, Promise,
-    PromiseOrValue,
+    assert_one_yocto, env, near, near_bindgen, require, AccountId, Gas, GasWeight, NearToken,
+    Promise,
 };
 
+// Prepaid gas for a `check_upgrade` call after migration
+const CHECK_UPGRADE_CALL_GAS: Gas = Gas::from_tgas(10);
+
+// Storage key of the invariants taken by `migrate` until they are checked by `check_upgrade`
+const UPGRADE_INVARIANTS_KEY: &[u8] = b"UPGRADE_INVARIANTS";
+
+/// Contract code staged for deployment once the upgrade delay has passed
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct StagedUpgrade {
+    /// Hex encoded sha256 of the staged code
+    pub code_hash: String,
+    pub staged_by: AccountId,
+    pub activation_height: u64,
+}
+
+/// State before an upgrade which has to be preserved by the migration
+#[near(serializers = [borsh])]
+pub struct UpgradeInvariants {
+    pub state_version: u32,
+    pub owner_id: Option<AccountId>,
+    pub worker_count: u32,
+    pub pending_request_count: u32,
+}
+
 #[near_bindgen]
 impl Contract {
     #[init(ignore_state)]
@@ -12,12 +44,70 @@ impl Contract {
     #[private]
     pub fn migrate() -> Self {
         assert_one_yocto();
-        env::state_read::<Self>().expect("Failed to read contract state")
+        let from_version = read_state_version();
+        let state = VersionedContract::read();
+        // Taken from the state right before the migration, so requests and registrations
+        // landing between `deploy_staged_upgrade` and the deployment don't fail the self-check
+        write_upgrade_invariants(&state.invariants());
+        let contract = state.migrate();
+        write_state_version();
+
+        Event::StateMigrated {
+            from_version,
+            to_version: CURRENT_STATE_VERSION,
+        }
+        .emit();
+
+        contract
+    }
+
+    /// Stage the code passed as input for an upgrade. Only its hash is stored, the same
+    /// code has to be provided again to deploy it after the upgrade delay.
+    pub fn stage_upgrade(&mut self) {
+        self.assert_role(Role::Upgrader);
+        let code = env::input().expect("Code not found");
+        let code_hash = encode(env::sha256_array(&code));
+        let activation_height = env::block_height() + self.upgrade_delay_blocks;
+
+        Event::UpgradeStaged {
+            code_hash: code_hash.clone(),
+            activation_height,
+        }
+        .emit();
+
+        self.staged_upgrade = Some(StagedUpgrade {
+            code_hash,
+            staged_by: env::predecessor_account_id(),
+            activation_height,
+        });
     }
 
-    pub fn upgrade(&mut self) -> PromiseOrValue<AccountId> {
-        self.assert_owner();
+    pub fn cancel_staged_upgrade(&mut self) {
+        self.assert_role(Role::Upgrader);
+        let staged_upgrade = self.staged_upgrade.take().expect("No staged upgrade");
+
+        Event::UpgradeCancelled {
+            code_hash: staged_upgrade.code_hash,
+        }
+        .emit();
+    }
+
+    /// Deploy the staged code passed as input once the upgrade delay has passed.
+    /// The deployment, migration and self-check run in a single batch, so if the self-check
+    /// fails the whole batch is reverted and the previous code and state are kept.
+    pub fn deploy_staged_upgrade(&mut self) -> Promise {
+        self.assert_role(Role::Upgrader);
+        let staged_upgrade = self.staged_upgrade.clone().expect("No staged upgrade");
+        require!(
+            env::block_height() >= staged_upgrade.activation_height,
+            "Upgrade delay has not passed"
+        );
         let code = env::input().expect("Code not found");
+        require!(
+            encode(env::sha256_array(&code)) == staged_upgrade.code_hash,
+            "Code does not match the staged upgrade"
+        );
+
         Promise::new(env::current_account_id())
             .deploy_contract(code)
             .function_call_weight(
@@ -28,12 +118,47 @@ impl Contract {
                 GasWeight(1),
             )
             .function_call_weight(
-                "get_owner_id".into(),
-                vec![],
+                "check_upgrade".into(),
+                serde_json::to_vec(&json!({ "code_hash": staged_upgrade.code_hash })).unwrap(),
                 NearToken::from_millinear(0),
-                Gas::from_tgas(10),
+                CHECK_UPGRADE_CALL_GAS,
                 GasWeight(0),
             )
-            .into()
     }
+
+    /// Self-check of the new code after migration. Panics if the state doesn't match the
+    /// state before the migration, which reverts the deployment.
+    #[private]
+    pub fn check_upgrade(&mut self, code_hash: String) {
+        let invariants = take_upgrade_invariants().expect("Upgrade invariants not found");
+        let state_version = read_state_version();
+        require!(
+            state_version == CURRENT_STATE_VERSION && state_version >= invariants.state_version,
+            "State version is not migrated"
+        );
+        require!(self.owner_id == invariants.owner_id, "Owner changed");
+        require!(
+            self.worker_by_account_id.len() == invariants.worker_count,
+            "Worker count changed"
+        );
+        require!(
+            self.pending_requests.len() == invariants.pending_request_count,
+            "Pending request count changed"
+        );
+
+        self.staged_upgrade = None;
+        self.previous_code_hash = self.code_hash.replace(code_hash.clone());
+
+        Event::UpgradeDeployed { code_hash }.emit();
+    }
+}
+
+pub(crate) fn write_upgrade_invariants(invariants: &UpgradeInvariants) {
+    env::storage_write(UPGRADE_INVARIANTS_KEY, &borsh::to_vec(invariants).unwrap());
+}
+
+fn take_upgrade_invariants() -> Option<UpgradeInvariants> {
+    let invariants = env::storage_read(UPGRADE_INVARIANTS_KEY)?;
+    env::storage_remove(UPGRADE_INVARIANTS_KEY);
+    Some(UpgradeInvariants::try_from_slice(&invariants).expect("Invalid upgrade invariants"))
 }
@@ -3,10 +3,110 @@ use near_sdk::AccountId;
 
 #[near]
 impl Contract {
-    pub fn get_owner_id(&self) -> AccountId {
+    pub fn get_state_version(&self) -> u32 {
+        read_state_version()
+    }
+
+    pub fn get_owner_id(&self) -> Option<AccountId> {
         self.owner_id.clone()
     }
 
+    pub fn get_proposed_owner_id(&self) -> Option<AccountId> {
+        self.proposed_owner_id.clone()
+    }
+
+    pub fn has_role(&self, account_id: AccountId, role: Role) -> bool {
+        self.roles
+            .get(&account_id)
+            .is_some_and(|roles| roles.contains(&role))
+    }
+
+    pub fn get_roles(&self, account_id: AccountId) -> Vec<Role> {
+        self.roles.get(&account_id).cloned().unwrap_or_default()
+    }
+
+    pub fn get_role_members(&self, role: Role) -> Vec<&AccountId> {
+        self.roles
+            .iter()
+            .filter(|(_, roles)| roles.contains(&role))
+            .map(|(account_id, _)| account_id)
+            .collect()
+    }
+
+    pub fn is_paused(&self, method: PausableMethod) -> bool {
+        self.paused_methods.contains(&method)
+    }
+
+    pub fn get_paused_methods(&self) -> Vec<PausableMethod> {
+        self.paused_methods.clone()
+    }
+
+    pub fn get_approved_codehashes(
+        &self,
+        offset: u32,
+        limit: u32,
+    ) -> Vec<(&String, &CodehashMetadata)> {
+        self.approved_codehashes
+            .iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    pub fn get_codehash(&self, codehash: String) -> Option<&CodehashMetadata> {
+        self.approved_codehashes.get(&codehash)
+    }
+
+    pub fn get_approval_threshold(&self) -> u32 {
+        self.approval_threshold
+    }
+
+    pub fn get_proposal_expiry_blocks(&self) -> u64 {
+        self.proposal_expiry_blocks
+    }
+
+    pub fn get_proposal(&self, proposal_id: u64) -> Option<&CodehashProposal> {
+        self.codehash_proposals.get(&proposal_id)
+    }
+
+    pub fn get_proposals(&self, offset: u32, limit: u32) -> Vec<&CodehashProposal> {
+        self.codehash_proposals
+            .values()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    pub fn get_council_proposal(&self, proposal_id: u64) -> Option<&CouncilProposal> {
+        self.council_proposals.get(&proposal_id)
+    }
+
+    pub fn get_council_proposals(&self, offset: u32, limit: u32) -> Vec<&CouncilProposal> {
+        self.council_proposals
+            .values()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    pub fn get_staged_upgrade(&self) -> Option<StagedUpgrade> {
+        self.staged_upgrade.clone()
+    }
+
+    /// Hex encoded sha256 of the code deployed by the last upgrade
+    pub fn get_code_hash(&self) -> Option<String> {
+        self.code_hash.clone()
+    }
+
+    /// Hex encoded sha256 of the code replaced by the last upgrade
+    pub fn get_previous_code_hash(&self) -> Option<String> {
+        self.previous_code_hash.clone()
+    }
+
+    pub fn get_upgrade_delay_blocks(&self) -> u64 {
+        self.upgrade_delay_blocks
+    }
+
     pub fn get_worker_len(&self) -> u32 {
         self.worker_by_account_id.len()
     }
@@ -15,6 +115,13 @@ impl Contract {
         self.worker_by_account_id.get(&account_id).cloned()
     }
 
+    /// Get the evidence of the attestation a worker registered with
+    pub fn get_worker_attestation(&self, account_id: AccountId) -> Option<&WorkerAttestation> {
+        self.worker_by_account_id
+            .get(&account_id)
+            .and_then(|worker| worker.attestation.as_ref())
+    }
+
     pub fn get_workers(&self, offset: u32, limit: u32) -> Vec<&Worker> {
         self.worker_by_account_id
             .values()
@@ -23,11 +130,159 @@ impl Contract {
             .collect()
     }
 
+    /// Get the pending requests which can still be responded to
     pub fn get_pending_requests(&self, offset: u64, limit: u64) -> Vec<&Request> {
         self.pending_requests
             .values()
+            .filter(|request| !self.is_request_timed_out(request))
             .skip(offset as usize)
             .take(limit as usize)
             .collect()
     }
+
+    /// Get pending requests with ids greater than `request_id` in id order, so workers can
+    /// resume polling from the last processed request. Timed out requests are skipped.
+    pub fn get_pending_requests_since(&self, request_id: u64, limit: u32) -> Vec<&Request> {
+        self.pending_request_ids
+            .iter_from(request_id)
+            .filter_map(|(request_id, _)| self.pending_requests.get(&request_id))
+            .filter(|request| !self.is_request_timed_out(request))
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Get a request by id, with its status. Completed requests are kept for the result
+    /// retention period.
+    pub fn get_request(&self, request_id: u64) -> Option<RequestRecord> {
+        self.request_records.get(&request_id)
+    }
+
+    /// Get requests of an account, newest first. Pass the id of the last returned request as
+    /// `before` to get the next page.
+    pub fn get_account_requests(
+        &self,
+        account_id: AccountId,
+        before: Option<u64>,
+        limit: u32,
+    ) -> Vec<RequestRecord> {
+        self.account_requests
+            .iter_rev_from((account_id.clone(), before.unwrap_or(u64::MAX)))
+            .take_while(|((request_account_id, _), _)| *request_account_id == account_id)
+            .take(limit as usize)
+            .filter_map(|((_, request_id), _)| self.request_records.get(&request_id))
+            .collect()
+    }
+
+    /// Get the most recently fulfilled requests, newest first
+    pub fn get_recent_fulfilled_requests(&self, limit: u32) -> Vec<RequestRecord> {
+        let count = self.recent_fulfilled.len().min(limit) as u64;
+        (0..count)
+            .map(|i| (self.stats.fulfilled - 1 - i) % RECENT_FULFILLED_CAPACITY)
+            .filter_map(|index| self.recent_fulfilled.get(index as u32))
+            .filter_map(|request_id| self.request_records.get(request_id))
+            .collect()
+    }
+
+    /// Verify a response was produced by a registered worker, e.g. for auditing past results
+    pub fn verify_response(
+        &self,
+        request_id: u64,
+        random_seed: Vec<u8>,
+        random_number: Vec<u8>,
+        signature: Vec<u8>,
+        worker_id: AccountId,
+    ) -> ResponseVerification {
+        let Some(worker) = self.worker_by_account_id.get(&worker_id) else {
+            return ResponseVerification {
+                signature_valid: false,
+                codehash_approved: false,
+            };
+        };
+
+        let signature_valid = signature::ed25519_public_key_bytes(&worker.public_key)
+            .map(|public_key| {
+                signature::verify_response_signature(
+                    &public_key,
+                    request_id,
+                    &random_seed,
+                    &random_number,
+                    &signature,
+                )
+            })
+            .unwrap_or(false);
+
+        ResponseVerification {
+            signature_valid,
+            codehash_approved: self.is_codehash_approved(&worker.codehash),
+        }
+    }
+
+    /// Dry run the checks of `register_worker` on a quote of `public_key`, e.g. before spending
+    /// gas on the registration. Failed checks are reported instead of panicking.
+    pub fn verify_registration(
+        &self,
+        quote_hex: String,
+        collateral: String,
+        tcb_info: String,
+        public_key: PublicKey,
+    ) -> RegistrationReport {
+        let now = block_timestamp() / 1000000000;
+        let mut verification = verifier::verify_registration(
+            &quote_hex,
+            &collateral,
+            &tcb_info,
+            &public_key,
+            now,
+            None,
+        );
+        verification.codehash_approved = verification
+            .codehash
+            .as_ref()
+            .ok()
+            .map(|codehash| self.is_codehash_approved(codehash));
+
+        verification.into()
+    }
+
+    /// Get the result of a fulfilled request if it's still retained
+    pub fn get_result(&self, request_id: u64) -> Option<&FulfilledResult> {
+        self.fulfilled_results
+            .get(&request_id)
+            .filter(|result| !self.is_result_expired(result))
+    }
+
+    pub fn get_result_retention_blocks(&self) -> u64 {
+        self.result_retention_blocks
+    }
+
+    pub fn get_ft_price(&self, token_id: AccountId) -> Option<U128> {
+        self.ft_prices.get(&token_id).copied()
+    }
+
+    pub fn get_ft_prices(&self) -> Vec<(&AccountId, &U128)> {
+        self.ft_prices.iter().collect()
+    }
+
+    pub fn get_stats(&self) -> StatsView {
+        (&self.stats).into()
+    }
+
+    pub fn get_worker_stats(&self, account_id: AccountId) -> Option<WorkerStatsView> {
+        self.worker_stats.get(&account_id).map(Into::into)
+    }
+
+    /// Whether requests are accepted and at least one worker with an approved codehash and a
+    /// fresh attestation is registered
+    pub fn is_service_available(&self) -> bool {
+        !self.is_paused(PausableMethod::Request)
+            && !self.is_paused(PausableMethod::Respond)
+            && self.worker_by_account_id.values().any(|worker| {
+                self.is_codehash_approved(&worker.codehash)
+                    && worker.is_attestation_fresh_at(block_timestamp() / 1_000_000_000)
+            })
+    }
+
+    pub fn get_ft_fees(&self) -> Vec<(&AccountId, &U128)> {
+        self.ft_fees.iter().collect()
+    }
 }
@@ -0,0 +1,90 @@
+//! Helpers for contracts consuming randomness from the TEE RNG contract.
+//!
+//! Request randomness with [`request_randomness`] and implement [`RandomnessConsumer`] to receive
+//! it. [`impl_randomness_callback!`] generates the callback which decodes the result and
+//! dispatches it to the trait. [`RandomBytes`] derives unbiased ranges, shuffles and picks from
+//! the received bytes.
+
+use near_sdk::serde_json::json;
+use near_sdk::{env, AccountId, Gas, NearToken, Promise};
+
+pub use tee_rng_sdk::{self, ext_tee_rng, MIN_REQUEST_DEPOSIT, REQUEST_EXPIRATION_BLOCKS};
+
+pub use crate::random::RandomBytes;
+
+mod random;
+
+/// Prepaid gas for a `request` call, which includes the gas for resuming the request
+pub const REQUEST_CALL_GAS: Gas = Gas::from_tgas(50);
+
+/// Prepaid gas for the `on_tee_rng_response` callback
+pub const ON_TEE_RNG_RESPONSE_CALL_GAS: Gas = Gas::from_tgas(20);
+
+/// Name of the callback generated by [`impl_randomness_callback!`]
+pub const ON_TEE_RNG_RESPONSE_METHOD: &str = "on_tee_rng_response";
+
+/// Receives randomness requested with [`request_randomness`]
+pub trait RandomnessConsumer {
+    /// Called with the random bytes of a fulfilled request
+    fn on_randomness(&mut self, request_id: u64, random_bytes: Vec<u8>);
+
+    /// Called if the request timed out, was cancelled or failed. The deposit is refunded by the
+    /// RNG contract.
+    fn on_randomness_failed(&mut self, request_id: u64) {
+        env::log_str(&format!("Randomness request {} failed", request_id));
+    }
+}
+
+/// Request randomness from the RNG contract and call back `on_tee_rng_response` of the current
+/// contract with the result. `request_id` is chosen by the caller to match the callback to its
+/// request, e.g. the id of a game round.
+pub fn request_randomness(rng_account_id: AccountId, request_id: u64) -> Promise {
+    ext_tee_rng::ext(rng_account_id)
+        .with_attached_deposit(MIN_REQUEST_DEPOSIT)
+        .with_static_gas(REQUEST_CALL_GAS)
+        .request()
+        .then(Promise::new(env::current_account_id()).function_call(
+            ON_TEE_RNG_RESPONSE_METHOD.to_string(),
+            json!({ "request_id": request_id }).to_string().into_bytes(),
+            NearToken::from_yoctonear(0),
+            ON_TEE_RNG_RESPONSE_CALL_GAS,
+        ))
+}
+
+/// Generate the `on_tee_rng_response` callback for a contract implementing
+/// [`RandomnessConsumer`]
+///
+/// ```ignore
+/// impl RandomnessConsumer for Contract {
+///     fn on_randomness(&mut self, request_id: u64, random_bytes: Vec<u8>) {
+///         let mut random = RandomBytes::new(&random_bytes);
+///         let winner = random.gen_range(0, self.players.len() as u64);
+///     }
+/// }
+///
+/// impl_randomness_callback!(Contract);
+/// ```
+#[macro_export]
+macro_rules! impl_randomness_callback {
+    ($contract:ident) => {
+        #[::near_sdk::near]
+        impl $contract {
+            #[private]
+            pub fn on_tee_rng_response(
+                &mut self,
+                request_id: u64,
+                #[callback_result] result: Result<Option<String>, ::near_sdk::PromiseError>,
+            ) {
+                let random_bytes = result.ok().flatten().and_then(|random_number| {
+                    $crate::tee_rng_sdk::decode_random_number(&random_number).ok()
+                });
+                match random_bytes {
+                    Some(random_bytes) => {
+                        $crate::RandomnessConsumer::on_randomness(self, request_id, random_bytes)
+                    }
+                    None => $crate::RandomnessConsumer::on_randomness_failed(self, request_id),
+                }
+            }
+        }
+    };
+}
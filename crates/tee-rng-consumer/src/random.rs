@@ -0,0 +1,152 @@
+use near_sdk::env;
+
+/// Deterministic stream of random numbers derived from the bytes returned by the RNG contract.
+/// The bytes are expanded with sha256, so any number of values can be drawn from one response.
+pub struct RandomBytes {
+    seed: Vec<u8>,
+    counter: u64,
+    buffer: [u8; 32],
+    position: usize,
+}
+
+impl RandomBytes {
+    pub fn new(seed: &[u8]) -> Self {
+        Self {
+            seed: seed.to_vec(),
+            counter: 0,
+            buffer: [0; 32],
+            // Fill the buffer on the first draw
+            position: 32,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        if self.position + 8 > self.buffer.len() {
+            self.refill();
+        }
+        let bytes = self.buffer[self.position..self.position + 8]
+            .try_into()
+            .unwrap();
+        self.position += 8;
+        u64::from_le_bytes(bytes)
+    }
+
+    /// Draw a number in `[low, high)` without modulo bias
+    pub fn gen_range(&mut self, low: u64, high: u64) -> u64 {
+        assert!(low < high, "Empty range");
+        let range = high - low;
+        // Reject the values of the last incomplete interval of `range` values
+        let remainder = (u64::MAX % range + 1) % range;
+        let max = u64::MAX - remainder;
+        loop {
+            let value = self.next_u64();
+            if value <= max {
+                return low + value % range;
+            }
+        }
+    }
+
+    /// Shuffle the items in place with the Fisher-Yates algorithm
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.gen_range(0, i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+
+    /// Pick one of the items, or `None` if there are none
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        let index = self.gen_range(0, items.len() as u64) as usize;
+        items.get(index)
+    }
+
+    /// Pick `count` distinct items in random order, or all of them if there are fewer
+    pub fn pick_many<'a, T>(&mut self, items: &'a [T], count: usize) -> Vec<&'a T> {
+        let mut items: Vec<&T> = items.iter().collect();
+        let count = count.min(items.len());
+        // Partial Fisher-Yates, only the first `count` positions are needed
+        for i in 0..count {
+            let j = self.gen_range(i as u64, items.len() as u64) as usize;
+            items.swap(i, j);
+        }
+        items.truncate(count);
+        items
+    }
+
+    fn refill(&mut self) {
+        let mut input = self.seed.clone();
+        input.extend_from_slice(&self.counter.to_le_bytes());
+        self.buffer = env::sha256_array(&input);
+        self.counter += 1;
+        self.position = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed() -> Vec<u8> {
+        (0..32).collect()
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let mut a = RandomBytes::new(&seed());
+        let mut b = RandomBytes::new(&seed());
+        let values: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        assert_eq!(values, (0..10).map(|_| b.next_u64()).collect::<Vec<_>>());
+
+        let mut c = RandomBytes::new(&[1]);
+        assert_ne!(values[0], c.next_u64());
+    }
+
+    #[test]
+    fn test_gen_range() {
+        let mut random = RandomBytes::new(&seed());
+        let mut counts = [0; 3];
+        for _ in 0..3000 {
+            let value = random.gen_range(10, 13);
+            assert!((10..13).contains(&value));
+            counts[(value - 10) as usize] += 1;
+        }
+        assert!(counts.iter().all(|count| *count > 900));
+
+        assert_eq!(random.gen_range(5, 6), 5);
+        assert!(random.gen_range(0, u64::MAX) < u64::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "Empty range")]
+    fn test_gen_empty_range() {
+        RandomBytes::new(&seed()).gen_range(1, 1);
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let mut random = RandomBytes::new(&seed());
+        let mut items: Vec<u32> = (0..20).collect();
+        random.shuffle(&mut items);
+        assert_ne!(items, (0..20).collect::<Vec<_>>());
+
+        items.sort();
+        assert_eq!(items, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_pick() {
+        let mut random = RandomBytes::new(&seed());
+        let items = ["a", "b", "c"];
+        assert!(items.contains(random.pick(&items).unwrap()));
+        assert_eq!(random.pick::<u8>(&[]), None);
+
+        let mut picked = random.pick_many(&items, 2);
+        assert_eq!(picked.len(), 2);
+        picked.dedup();
+        assert_eq!(picked.len(), 2);
+        assert_eq!(random.pick_many(&items, 5).len(), 3);
+    }
+}
@@ -0,0 +1,165 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{AccountId, PublicKey};
+
+use crate::{CodehashAction, CouncilAction, PausableMethod, Payment, Role};
+
+/// Owned version of the events emitted by the contract, which can be deserialized from logs
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(
+    crate = "near_sdk::serde",
+    rename_all = "snake_case",
+    tag = "event",
+    content = "data"
+)]
+pub enum Event {
+    ContractInitialized {
+        owner_id: AccountId,
+    },
+    StateMigrated {
+        from_version: u32,
+        to_version: u32,
+    },
+    WorkerRegistered {
+        worker_id: AccountId,
+        public_key: PublicKey,
+        codehash: String,
+        checksum: String,
+    },
+    Request {
+        account_id: AccountId,
+        request_id: u64,
+        random_seed: Vec<u8>,
+        payment: Payment,
+        block_height: u64,
+    },
+    Response {
+        worker_id: AccountId,
+        request_id: u64,
+        random_number: Vec<u8>,
+        /// Worker signature of the response, missing from events before version 1.2.0
+        #[serde(default)]
+        signature: Vec<u8>,
+    },
+    RequestCancelled {
+        account_id: AccountId,
+        request_id: u64,
+    },
+    RequestFailed {
+        account_id: AccountId,
+        request_id: u64,
+    },
+    RequestPurged {
+        request_id: u64,
+    },
+    RequestTimedOut {
+        account_id: AccountId,
+        request_id: u64,
+    },
+    DepositRefunded {
+        account_id: AccountId,
+        request_id: u64,
+        payment: Payment,
+    },
+    ResultClaimed {
+        account_id: AccountId,
+        request_id: u64,
+    },
+    ResultPurged {
+        request_id: u64,
+    },
+    RequestHistoryPurged {
+        request_id: u64,
+    },
+    RoleGranted {
+        account_id: AccountId,
+        role: Role,
+    },
+    RoleRevoked {
+        account_id: AccountId,
+        role: Role,
+    },
+    OwnershipTransferProposed {
+        owner_id: AccountId,
+        proposed_owner_id: AccountId,
+    },
+    OwnershipTransferred {
+        previous_owner_id: AccountId,
+        new_owner_id: AccountId,
+    },
+    OwnershipTransferCancelled {
+        proposed_owner_id: AccountId,
+    },
+    OwnershipRenounced {
+        previous_owner_id: AccountId,
+    },
+    CodehashProposed {
+        proposal_id: u64,
+        proposer: AccountId,
+        action: CodehashAction,
+        codehash: String,
+    },
+    CodehashVoted {
+        proposal_id: u64,
+        approver: AccountId,
+    },
+    CodehashApproved {
+        codehash: String,
+    },
+    CodehashRevoked {
+        codehash: String,
+    },
+    CouncilProposed {
+        proposal_id: u64,
+        proposer: AccountId,
+        action: CouncilAction,
+    },
+    CouncilVoted {
+        proposal_id: u64,
+        approver: AccountId,
+    },
+    UpgradeStaged {
+        code_hash: String,
+        activation_height: u64,
+    },
+    UpgradeCancelled {
+        code_hash: String,
+    },
+    UpgradeDeployed {
+        code_hash: String,
+    },
+    ApprovalThresholdUpdated {
+        threshold: u32,
+    },
+    ProposalExpiryBlocksUpdated {
+        blocks: u64,
+    },
+    UpgradeDelayBlocksUpdated {
+        blocks: u64,
+    },
+    ResultRetentionBlocksUpdated {
+        blocks: u64,
+    },
+    FtPriceSet {
+        token_id: AccountId,
+        price: U128,
+    },
+    FtPriceRemoved {
+        token_id: AccountId,
+    },
+    FtFeesWithdrawn {
+        token_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    },
+    FtFeesWithdrawalFailed {
+        token_id: AccountId,
+        amount: U128,
+    },
+    Paused {
+        method: PausableMethod,
+    },
+    Unpaused {
+        method: PausableMethod,
+    },
+}
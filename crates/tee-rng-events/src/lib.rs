@@ -0,0 +1,198 @@
+//! Events emitted by the TEE RNG contract and the types they carry, shared by the contract and
+//! off-chain services. Indexers can parse the contract logs with [`parse_event_log`].
+
+use std::fmt;
+
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::{self, json, Value};
+
+pub use crate::event::Event;
+pub use crate::types::*;
+
+mod event;
+mod types;
+
+pub const EVENT_STANDARD: &str = "tee-rng";
+pub const EVENT_STANDARD_VERSION: &str = "1.4.0";
+
+/// Prefix of NEP-297 event logs
+pub const EVENT_JSON_PREFIX: &str = "EVENT_JSON:";
+
+/// An event parsed from a contract log
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EventLog {
+    pub standard: String,
+    pub version: String,
+    pub event: Event,
+}
+
+#[derive(Debug)]
+pub enum ParseEventError {
+    /// The log is not a NEP-297 event
+    NotAnEvent,
+    /// The event belongs to another standard
+    UnknownStandard(String),
+    /// The event doesn't carry exactly one data entry
+    InvalidData,
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for ParseEventError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAnEvent => write!(f, "Log is not an event"),
+            Self::UnknownStandard(standard) => write!(f, "Unknown event standard {}", standard),
+            Self::InvalidData => write!(f, "Event must have exactly one data entry"),
+            Self::Json(error) => write!(f, "Invalid event JSON: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ParseEventError {}
+
+impl From<serde_json::Error> for ParseEventError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+struct RawEventLog {
+    standard: String,
+    version: String,
+    event: String,
+    data: Vec<Value>,
+}
+
+/// Format an event the way the contract logs it. `event` has to serialize with the `event` tag
+/// and `data` content, like [`Event`].
+pub fn format_event_log<T: Serialize>(event: &T) -> String {
+    format_log(EVENT_STANDARD, EVENT_STANDARD_VERSION, event)
+}
+
+fn format_log<T: Serialize>(standard: &str, version: &str, event: &T) -> String {
+    let json = json!(event);
+    let event_json = json!({
+        "standard": standard,
+        "version": version,
+        "event": json["event"],
+        "data": [json["data"]]
+    });
+    format!("{}{}", EVENT_JSON_PREFIX, event_json)
+}
+
+/// Parse a log line emitted by the contract
+pub fn parse_event_log(log: &str) -> Result<EventLog, ParseEventError> {
+    let event_json = log
+        .strip_prefix(EVENT_JSON_PREFIX)
+        .ok_or(ParseEventError::NotAnEvent)?;
+    let raw: RawEventLog = serde_json::from_str(event_json)?;
+    if raw.standard != EVENT_STANDARD {
+        return Err(ParseEventError::UnknownStandard(raw.standard));
+    }
+
+    let [data]: [Value; 1] = raw
+        .data
+        .try_into()
+        .map_err(|_| ParseEventError::InvalidData)?;
+    let event = serde_json::from_value(json!({ "event": raw.event, "data": data }))?;
+
+    Ok(EventLog {
+        standard: raw.standard,
+        version: raw.version,
+        event,
+    })
+}
+
+impl fmt::Display for EventLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_log(&self.standard, &self.version, &self.event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::json_types::U128;
+    use near_sdk::NearToken;
+
+    #[test]
+    fn test_parse_event_log() {
+        let log = r#"EVENT_JSON:{"data":[{"account_id":"alice.near","block_height":100,"payment":{"Near":"5000000000000000000000"},"random_seed":[1,2,3],"request_id":1}],"event":"request","standard":"tee-rng","version":"1.1.0"}"#;
+
+        let event_log = parse_event_log(log).unwrap();
+        assert_eq!(event_log.standard, EVENT_STANDARD);
+        assert_eq!(event_log.version, "1.1.0");
+        assert_eq!(
+            event_log.event,
+            Event::Request {
+                account_id: "alice.near".parse().unwrap(),
+                request_id: 1,
+                random_seed: vec![1, 2, 3],
+                payment: Payment::Near(NearToken::from_millinear(5)),
+                block_height: 100,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_response_event_log_without_signature() {
+        let log = r#"EVENT_JSON:{"data":[{"random_number":[4,5,6],"request_id":1,"worker_id":"worker.near"}],"event":"response","standard":"tee-rng","version":"1.1.0"}"#;
+
+        let event_log = parse_event_log(log).unwrap();
+        assert_eq!(
+            event_log.event,
+            Event::Response {
+                worker_id: "worker.near".parse().unwrap(),
+                request_id: 1,
+                random_number: vec![4, 5, 6],
+                signature: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_format_and_parse_event_log() {
+        let event = Event::DepositRefunded {
+            account_id: "alice.near".parse().unwrap(),
+            request_id: 7,
+            payment: Payment::FungibleToken {
+                token_id: "usdt.near".parse().unwrap(),
+                amount: U128(1_000_000),
+            },
+        };
+
+        let log = format_event_log(&event);
+        let event_log = parse_event_log(&log).unwrap();
+        assert_eq!(event_log.event, event);
+        assert_eq!(event_log.version, EVENT_STANDARD_VERSION);
+        assert_eq!(event_log.to_string(), log);
+    }
+
+    #[test]
+    fn test_parse_invalid_event_log() {
+        assert!(matches!(
+            parse_event_log("Request received"),
+            Err(ParseEventError::NotAnEvent)
+        ));
+        assert!(matches!(
+            parse_event_log(
+                r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_mint","data":[{}]}"#
+            ),
+            Err(ParseEventError::UnknownStandard(_))
+        ));
+        assert!(matches!(
+            parse_event_log(
+                r#"EVENT_JSON:{"standard":"tee-rng","version":"1.1.0","event":"paused","data":[]}"#
+            ),
+            Err(ParseEventError::InvalidData)
+        ));
+        assert!(matches!(
+            parse_event_log(
+                r#"EVENT_JSON:{"standard":"tee-rng","version":"1.1.0","event":"unknown","data":[{}]}"#
+            ),
+            Err(ParseEventError::Json(_))
+        ));
+    }
+}
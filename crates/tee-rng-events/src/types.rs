@@ -0,0 +1,56 @@
+use near_sdk::json_types::U128;
+use near_sdk::{near, AccountId, NearToken};
+
+/// Roles granted by the owner to delegate admin operations
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    CodehashManager,
+    Pauser,
+    Treasurer,
+    Upgrader,
+}
+
+impl Role {
+    pub const ALL: [Role; 4] = [
+        Role::CodehashManager,
+        Role::Pauser,
+        Role::Treasurer,
+        Role::Upgrader,
+    ];
+}
+
+/// Methods which can be paused individually in an emergency
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PausableMethod {
+    Request,
+    Respond,
+    RegisterWorker,
+}
+
+/// How a request was paid for, so the deposit can be refunded in the same asset
+#[near(serializers = [json, borsh])]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Payment {
+    Near(NearToken),
+    FungibleToken { token_id: AccountId, amount: U128 },
+}
+
+/// Change to the approved codehashes which needs to be voted by the approvers
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CodehashAction {
+    Approve,
+    Revoke,
+}
+
+/// Change to the council of approvers, i.e. the accounts with the `CodehashManager` role,
+/// which needs to be voted by the approvers
+#[near(serializers = [json, borsh])]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CouncilAction {
+    AddApprover(AccountId),
+    RemoveApprover(AccountId),
+    SetApprovalThreshold(u32),
+}
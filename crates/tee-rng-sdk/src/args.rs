@@ -0,0 +1,68 @@
+use near_sdk::near;
+
+use crate::Response;
+
+/// Arguments of `request`. It takes none, the deposit is attached instead.
+#[near(serializers = [json])]
+#[derive(Clone, Default)]
+pub struct RequestArgs {}
+
+/// Arguments of `respond`, called by workers
+#[near(serializers = [json])]
+#[derive(Clone)]
+pub struct RespondArgs {
+    pub response: Response,
+}
+
+/// Arguments of `register_worker`, called by workers with the attestation of their key
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct RegisterWorkerArgs {
+    /// Hex encoded TDX quote with the worker public key in the report data
+    pub quote_hex: String,
+    /// JSON of the quote collateral
+    pub collateral: String,
+    /// Checksum of the quote uploaded to the proof explorer
+    pub checksum: String,
+    /// JSON of the TCB info reported by dstack
+    pub tcb_info: String,
+}
+
+/// Arguments of `cancel_request`
+#[near(serializers = [json])]
+#[derive(Clone)]
+pub struct CancelRequestArgs {
+    pub request_id: u64,
+}
+
+/// Arguments of `claim_result`
+#[near(serializers = [json])]
+#[derive(Clone)]
+pub struct ClaimResultArgs {
+    pub request_id: u64,
+}
+
+/// Message attached to `ft_transfer_call` when paying for a request with fungible tokens
+#[near(serializers = [json])]
+#[serde(rename_all = "snake_case")]
+pub enum FtTransferMessage {
+    /// Request a random number, the result can be found in the `response` event
+    Request {},
+}
+
+impl FtTransferMessage {
+    /// The `msg` argument of `ft_transfer_call`
+    pub fn to_msg(&self) -> String {
+        near_sdk::serde_json::to_string(self).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ft_transfer_message() {
+        assert_eq!(FtTransferMessage::Request {}.to_msg(), r#"{"request":{}}"#);
+    }
+}
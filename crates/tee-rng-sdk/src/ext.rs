@@ -0,0 +1,52 @@
+use near_sdk::{ext_contract, AccountId};
+
+use crate::{
+    FulfilledResult, Request, RequestRecord, ResponseVerification, StatsView, Worker,
+    WorkerAttestation, WorkerStatsView,
+};
+
+/// Interface of the TEE RNG contract for cross-contract calls
+#[ext_contract(ext_tee_rng)]
+pub trait TeeRng {
+    /// Resolves to the random number as hex, or `None` if the request wasn't fulfilled.
+    /// Requires a deposit of at least `MIN_REQUEST_DEPOSIT`.
+    fn request(&mut self) -> Option<String>;
+
+    fn cancel_request(&mut self, request_id: u64);
+
+    fn claim_result(&mut self, request_id: u64) -> String;
+
+    fn get_request(&self, request_id: u64) -> Option<RequestRecord>;
+
+    fn get_account_requests(
+        &self,
+        account_id: AccountId,
+        before: Option<u64>,
+        limit: u32,
+    ) -> Vec<RequestRecord>;
+
+    fn get_recent_fulfilled_requests(&self, limit: u32) -> Vec<RequestRecord>;
+
+    fn get_pending_requests_since(&self, request_id: u64, limit: u32) -> Vec<Request>;
+
+    fn get_result(&self, request_id: u64) -> Option<FulfilledResult>;
+
+    fn get_worker(&self, account_id: AccountId) -> Option<Worker>;
+
+    fn get_worker_attestation(&self, account_id: AccountId) -> Option<WorkerAttestation>;
+
+    fn verify_response(
+        &self,
+        request_id: u64,
+        random_seed: Vec<u8>,
+        random_number: Vec<u8>,
+        signature: Vec<u8>,
+        worker_id: AccountId,
+    ) -> ResponseVerification;
+
+    fn get_stats(&self) -> StatsView;
+
+    fn get_worker_stats(&self, account_id: AccountId) -> Option<WorkerStatsView>;
+
+    fn is_service_available(&self) -> bool;
+}
@@ -0,0 +1,28 @@
+//! Types and helpers for integrating with the TEE RNG contract from Rust services and contracts.
+//! The contract uses the same types, so they can't drift from its interface.
+
+use near_sdk::NearToken;
+
+pub use tee_rng_events as events;
+pub use tee_rng_events::{CodehashAction, CouncilAction, PausableMethod, Payment, Role};
+
+pub use crate::args::*;
+pub use crate::ext::*;
+pub use crate::result::*;
+pub use crate::types::*;
+
+mod args;
+mod ext;
+mod result;
+mod types;
+
+/// Minimum deposit attached to `request`. It's refunded once the request is completed, minus a
+/// bounty for purging the request unless it failed.
+pub const MIN_REQUEST_DEPOSIT: NearToken = NearToken::from_millinear(5);
+
+/// Number of blocks after which a pending request is considered expired.
+/// The yield timeout is 200 blocks, with a margin for the timeout callback to execute.
+pub const REQUEST_EXPIRATION_BLOCKS: u64 = 210;
+
+/// Workers attested longer ago than this don't count towards the service availability
+pub const ATTESTATION_FRESHNESS_SECONDS: u64 = 7 * 24 * 60 * 60;
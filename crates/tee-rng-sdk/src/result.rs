@@ -0,0 +1,56 @@
+use std::fmt;
+
+use near_sdk::serde_json;
+
+#[derive(Debug)]
+pub enum ResultError {
+    Json(serde_json::Error),
+    Hex(hex::FromHexError),
+}
+
+impl fmt::Display for ResultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(error) => write!(f, "Invalid result JSON: {}", error),
+            Self::Hex(error) => write!(f, "Invalid random number hex: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ResultError {}
+
+/// Decode a random number returned as hex by `request` or `claim_result`
+pub fn decode_random_number(random_number: &str) -> Result<Vec<u8>, ResultError> {
+    hex::decode(random_number).map_err(ResultError::Hex)
+}
+
+/// Decode the JSON value `request` resolves to, e.g. from a promise result in a callback.
+/// Returns `None` if the request wasn't fulfilled.
+pub fn decode_request_result(value: &[u8]) -> Result<Option<Vec<u8>>, ResultError> {
+    let random_number: Option<String> = serde_json::from_slice(value).map_err(ResultError::Json)?;
+    random_number
+        .map(|random_number| decode_random_number(&random_number))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_request_result() {
+        assert_eq!(
+            decode_request_result(b"\"00ff10\"").unwrap(),
+            Some(vec![0x00, 0xff, 0x10])
+        );
+        assert_eq!(decode_request_result(b"null").unwrap(), None);
+        assert!(matches!(
+            decode_request_result(b"\"xyz\""),
+            Err(ResultError::Hex(_))
+        ));
+        assert!(matches!(
+            decode_request_result(b"[1, 2]"),
+            Err(ResultError::Json(_))
+        ));
+    }
+}
@@ -0,0 +1,207 @@
+use near_sdk::{near, AccountId, CryptoHash, PublicKey};
+
+use crate::{Payment, ATTESTATION_FRESHNESS_SECONDS, REQUEST_EXPIRATION_BLOCKS};
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct Worker {
+    pub checksum: String,
+    pub codehash: String,
+    pub public_key: PublicKey,
+    /// Block timestamp of the attestation in seconds
+    pub attested_at: u64,
+    /// Evidence of the attestation, missing for workers registered before it was kept
+    pub attestation: Option<WorkerAttestation>,
+}
+
+impl Worker {
+    pub fn is_attestation_fresh_at(&self, timestamp_seconds: u64) -> bool {
+        timestamp_seconds <= self.attested_at + ATTESTATION_FRESHNESS_SECONDS
+    }
+}
+
+/// Evidence of the quote a worker registered with, kept to audit why it was trusted
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkerAttestation {
+    /// Hex encoded sha256 of the quote
+    pub quote_hash: String,
+    /// Hex encoded measurement of the initial TD contents
+    pub mrtd: String,
+    /// Hex encoded runtime measurement registers
+    pub rtmr0: String,
+    pub rtmr1: String,
+    pub rtmr2: String,
+    pub rtmr3: String,
+    /// TCB status of the verified quote, e.g. `UpToDate`
+    pub tcb_status: String,
+    /// Hex encoded FMSPC of the platform the quote was generated on
+    pub fmspc: String,
+    /// Block timestamp of the registration in seconds
+    pub registered_at: u64,
+}
+
+/// The index into calling the YieldResume feature of NEAR. This will allow to resume
+/// a yield call after the contract has been called back via this index.
+#[derive(Debug, Clone)]
+#[near(serializers=[borsh, json])]
+pub struct YieldIndex {
+    pub data_id: CryptoHash,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct Request {
+    pub request_id: u64,
+    pub account_id: AccountId,
+    pub payment: Payment,
+    pub random_seed: Vec<u8>,
+    pub yield_index: YieldIndex,
+    pub block_height: u64,
+}
+
+impl Request {
+    pub fn is_expired_at(&self, block_height: u64) -> bool {
+        block_height > self.block_height + REQUEST_EXPIRATION_BLOCKS
+    }
+}
+
+/// Result of a fulfilled request, kept so consumers can recover it if their callback failed
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct FulfilledResult {
+    pub request_id: u64,
+    pub account_id: AccountId,
+    pub random_number: Vec<u8>,
+    pub block_height: u64,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct Response {
+    pub request_id: u64,
+    pub random_number: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+#[near(serializers = [json, borsh])]
+#[serde(rename_all = "snake_case")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RequestStatus {
+    Pending,
+    Fulfilled,
+    TimedOut,
+    Cancelled,
+    Failed,
+}
+
+/// Request kept for queries, also after it's completed
+#[near(serializers = [json, borsh])]
+#[derive(Clone)]
+pub struct RequestRecord {
+    pub request_id: u64,
+    pub account_id: AccountId,
+    pub payment: Payment,
+    pub random_seed: Vec<u8>,
+    pub block_height: u64,
+    pub status: RequestStatus,
+    /// Worker which fulfilled the request
+    pub worker_id: Option<AccountId>,
+    /// Block height the request was completed at
+    pub completed_at: Option<u64>,
+}
+
+impl RequestRecord {
+    pub fn is_completed(&self) -> bool {
+        self.status != RequestStatus::Pending
+    }
+}
+
+#[near(serializers = [json])]
+pub struct ResponseVerification {
+    /// The response is signed by the worker's public key
+    pub signature_valid: bool,
+    /// The worker's codehash is still approved
+    pub codehash_approved: bool,
+}
+
+/// Outcome of the checks `register_worker` would run on a quote, see `verify_registration`
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct RegistrationReport {
+    /// The quote is verified against the collateral
+    pub quote_verified: bool,
+    /// TCB status of the verified quote
+    pub tcb_status: Option<String>,
+    /// The report data of the quote commits to the public key
+    pub report_data_matches: bool,
+    /// The event log of the TCB info replays to the rtmr3 of the quote
+    pub rtmr3_matches: bool,
+    /// Codehash of the worker image, if the TCB info is consistent with the quote
+    pub codehash: Option<String>,
+    /// The codehash is approved
+    pub codehash_approved: bool,
+    /// Why the failed checks failed, `register_worker` would panic with the first one
+    pub errors: Vec<String>,
+}
+
+impl RegistrationReport {
+    /// Whether `register_worker` would accept the quote
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty() && self.codehash_approved
+    }
+}
+
+/// Counters of requests by outcome
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Default)]
+pub struct RequestStats {
+    pub total_requests: u64,
+    pub fulfilled: u64,
+    pub timed_out: u64,
+    pub cancelled: u64,
+    pub failed: u64,
+    /// Sum of the blocks between request and response of fulfilled requests
+    pub total_latency_blocks: u64,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Default)]
+pub struct WorkerStats {
+    pub responses: u64,
+    /// Sum of the blocks between request and response of the worker's responses
+    pub total_latency_blocks: u64,
+    pub last_response_height: u64,
+}
+
+#[near(serializers = [json])]
+pub struct StatsView {
+    #[serde(flatten)]
+    pub stats: RequestStats,
+    pub average_latency_blocks: Option<u64>,
+}
+
+#[near(serializers = [json])]
+pub struct WorkerStatsView {
+    #[serde(flatten)]
+    pub stats: WorkerStats,
+    pub average_latency_blocks: Option<u64>,
+}
+
+impl From<&RequestStats> for StatsView {
+    fn from(stats: &RequestStats) -> Self {
+        Self {
+            stats: stats.clone(),
+            average_latency_blocks: stats.total_latency_blocks.checked_div(stats.fulfilled),
+        }
+    }
+}
+
+impl From<&WorkerStats> for WorkerStatsView {
+    fn from(stats: &WorkerStats) -> Self {
+        Self {
+            stats: stats.clone(),
+            average_latency_blocks: stats.total_latency_blocks.checked_div(stats.responses),
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use dcap_qvl::quote::TDReport10;
+use dcap_qvl::verify::{self, VerifiedReport};
+use dcap_qvl::QuoteCollateralV3;
+use hex::{decode, encode};
+use near_sdk::PublicKey;
+use serde_json::Value;
+use sha2::{Digest, Sha256, Sha384};
+use tee_rng_sdk::WorkerAttestation;
+
+/// Parse the quote collateral JSON passed to `register_worker`
+pub fn get_collateral(raw_quote_collateral: &str) -> Result<QuoteCollateralV3, String> {
+    let quote_collateral: Value = serde_json::from_str(raw_quote_collateral)
+        .map_err(|_| "TCB Info should be valid JSON".to_string())?;
+
+    Ok(QuoteCollateralV3 {
+        tcb_info_issuer_chain: str_field(&quote_collateral, "tcb_info_issuer_chain")?.to_owned(),
+        tcb_info: str_field(&quote_collateral, "tcb_info")?.to_owned(),
+        tcb_info_signature: hex_field(&quote_collateral, "tcb_info_signature")?,
+        qe_identity_issuer_chain: str_field(&quote_collateral, "qe_identity_issuer_chain")?
+            .to_owned(),
+        qe_identity: str_field(&quote_collateral, "qe_identity")?.to_owned(),
+        qe_identity_signature: hex_field(&quote_collateral, "qe_identity_signature")?,
+    })
+}
+
+/// Verify the quote against the collateral at `now` (seconds since the Unix epoch)
+pub fn verify_quote(
+    quote_hex: &str,
+    collateral: &QuoteCollateralV3,
+    now: u64,
+) -> Result<VerifiedReport, String> {
+    let quote = decode(quote_hex).map_err(|err| format!("Invalid quote hex: {}", err))?;
+    verify::verify(&quote, collateral, now)
+        .map_err(|err| format!("Report is not verified: {:?}", err))
+}
+
+/// The TD report of a verified TDX quote
+pub fn td_report(result: &VerifiedReport) -> Result<&TDReport10, String> {
+    result
+        .report
+        .as_td10()
+        .ok_or_else(|| "Report is not a TDX report".to_string())
+}
+
+/// Evidence of a verified quote to keep with the worker registered with it
+pub fn attestation_evidence(
+    quote_hex: &str,
+    collateral: &QuoteCollateralV3,
+    result: &VerifiedReport,
+    registered_at: u64,
+) -> Result<WorkerAttestation, String> {
+    let report = td_report(result)?;
+    let quote = decode(quote_hex).map_err(|err| format!("Invalid quote hex: {}", err))?;
+    // The FMSPC of the quote is checked against the one of the TCB info during verification
+    let tcb_info: Value = serde_json::from_str(&collateral.tcb_info)
+        .map_err(|_| "TCB Info should be valid JSON".to_string())?;
+
+    Ok(WorkerAttestation {
+        quote_hash: encode(Sha256::digest(quote)),
+        mrtd: encode(report.mr_td),
+        rtmr0: encode(report.rt_mr0),
+        rtmr1: encode(report.rt_mr1),
+        rtmr2: encode(report.rt_mr2),
+        rtmr3: encode(report.rt_mr3),
+        tcb_status: result.status.clone(),
+        fmspc: str_field(&tcb_info, "fmspc")?.to_lowercase(),
+        registered_at,
+    })
+}
+
+/// The report data a worker has to put in its quote: the hex of the public key string,
+/// padded with 0 to 128 characters
+pub fn report_data_for_public_key(public_key: &PublicKey) -> String {
+    let public_key_str: String = public_key.into();
+    format!("{:0>128}", encode(public_key_str))
+}
+
+/// Replay the event log of the TCB info against rtmr3 and extract the codehash of the worker
+/// image from the app compose
+pub fn verify_codehash(raw_tcb_info: &str, rtmr3: &str) -> Result<String, String> {
+    let tcb_info = parse_tcb_info(raw_tcb_info)?;
+    let event_log = event_log(&tcb_info)?;
+    // get compose hash from events
+    let expected_compose_hash = event_log
+        .iter()
+        .find(|e| e["event"].as_str() == Some("compose-hash"))
+        .and_then(|e| e["digest"].as_str())
+        .ok_or("Event log is missing the compose hash")?;
+
+    // replay the rtmr3 and compose hash
+    let replayed_rtmr3 = replay_rtmr(event_log, 3)?;
+    let app_compose = str_field(&tcb_info, "app_compose")?;
+    let replayed_compose_hash = replay_app_compose(app_compose);
+
+    // compose hash match expected
+    if replayed_compose_hash != expected_compose_hash {
+        return Err("Invalid compose hash".to_string());
+    }
+    // event with compose hash matches report rtmr3
+    if replayed_rtmr3 != rtmr3 {
+        return Err("Invalid rtmr3".to_string());
+    }
+
+    extract_codehash(app_compose).ok_or_else(|| "App compose is missing the image digest".into())
+}
+
+/// Replay the event log of the TCB info to the rtmr3 the quote should report
+pub fn replay_tcb_info_rtmr3(raw_tcb_info: &str) -> Result<String, String> {
+    let tcb_info = parse_tcb_info(raw_tcb_info)?;
+    replay_rtmr(event_log(&tcb_info)?, 3)
+}
+
+/// Replay the digests of the events measured into the given rtmr
+pub fn replay_rtmr(event_log: &[Value], imr: u8) -> Result<String, String> {
+    let mut digest = [0u8; 48];
+
+    for event in event_log {
+        let event_imr = event["imr"].as_u64().ok_or("Event is missing the imr")?;
+        if event_imr != imr as u64 {
+            continue;
+        }
+        let event_digest = hex_field(event, "digest")?;
+
+        // hash all digests together
+        let mut hasher = Sha384::new();
+        hasher.update(digest);
+        hasher.update(event_digest.as_slice());
+        digest = hasher.finalize().into();
+    }
+
+    // return hex encoded digest (rtmr[imr])
+    Ok(encode(digest))
+}
+
+/// The compose hash event digest of the app compose
+pub fn replay_app_compose(app_compose: &str) -> String {
+    // sha256 of app_compose from TcbInfo
+    let mut sha256 = Sha256::new();
+    sha256.update(app_compose);
+    let sha256bytes: [u8; 32] = sha256.finalize().into();
+
+    // sha384 of custom encoding: [phala_prefix]:[event_name]:[sha256_payload]
+    let mut hasher = Sha384::new();
+    hasher.update(vec![0x01, 0x00, 0x00, 0x08]);
+    hasher.update(b":");
+    hasher.update("compose-hash".as_bytes());
+    hasher.update(b":");
+    hasher.update(sha256bytes);
+    let digest: [u8; 48] = hasher.finalize().into();
+
+    encode(digest)
+}
+
+/// The sha256 digest of the first image in the docker compose file
+fn extract_codehash(app_compose: &str) -> Option<String> {
+    let (_, right) = app_compose.split_once("\\n    image:")?;
+    let (left, _) = right.split_once("\\n")?;
+    let (_, codehash) = left.split_once("@sha256:")?;
+
+    Some(codehash.to_owned())
+}
+
+fn parse_tcb_info(raw_tcb_info: &str) -> Result<Value, String> {
+    serde_json::from_str(raw_tcb_info).map_err(|_| "TCB Info should be valid JSON".to_string())
+}
+
+fn event_log(tcb_info: &Value) -> Result<&Vec<Value>, String> {
+    tcb_info["event_log"]
+        .as_array()
+        .ok_or_else(|| "TCB Info is missing the event log".to_string())
+}
+
+fn str_field<'a>(value: &'a Value, field: &str) -> Result<&'a str, String> {
+    value[field]
+        .as_str()
+        .ok_or_else(|| format!("Missing field {}", field))
+}
+
+fn hex_field(value: &Value, field: &str) -> Result<Vec<u8>, String> {
+    decode(str_field(value, field)?).map_err(|err| format!("Invalid hex in {}: {}", field, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn app_compose() -> String {
+        "{\"docker_compose_file\":\"services:\\n  worker:\\n    image: tee-rng/worker:latest@sha256:abcd\\n    platform: linux/amd64\\n\"}".to_string()
+    }
+
+    fn tcb_info(compose_hash: &str) -> Value {
+        json!({
+            "event_log": [
+                { "imr": 0, "event": "boot", "digest": "00".repeat(48) },
+                { "imr": 3, "event": "app-id", "digest": "11".repeat(48) },
+                { "imr": 3, "event": "compose-hash", "digest": compose_hash },
+            ],
+            "app_compose": app_compose(),
+        })
+    }
+
+    #[test]
+    fn test_verify_codehash() {
+        let compose_hash = replay_app_compose(&app_compose());
+        let tcb_info = tcb_info(&compose_hash);
+        let rtmr3 = replay_rtmr(tcb_info["event_log"].as_array().unwrap(), 3).unwrap();
+
+        let codehash = verify_codehash(&tcb_info.to_string(), &rtmr3).unwrap();
+        assert_eq!(codehash, "abcd");
+
+        assert_eq!(
+            verify_codehash(&tcb_info.to_string(), &"00".repeat(48)),
+            Err("Invalid rtmr3".to_string())
+        );
+    }
+
+    #[test]
+    fn test_verify_codehash_with_invalid_compose_hash() {
+        let tcb_info = tcb_info(&"22".repeat(48));
+        let rtmr3 = replay_rtmr(tcb_info["event_log"].as_array().unwrap(), 3).unwrap();
+
+        assert_eq!(
+            verify_codehash(&tcb_info.to_string(), &rtmr3),
+            Err("Invalid compose hash".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_collateral() {
+        let collateral = json!({
+            "tcb_info_issuer_chain": "chain",
+            "tcb_info": "{}",
+            "tcb_info_signature": "0102",
+            "qe_identity_issuer_chain": "chain",
+            "qe_identity": "{}",
+            "qe_identity_signature": "0304",
+        });
+
+        let collateral = get_collateral(&collateral.to_string()).unwrap();
+        assert_eq!(collateral.tcb_info_signature, vec![1, 2]);
+        assert_eq!(collateral.qe_identity_signature, vec![3, 4]);
+
+        assert_eq!(
+            get_collateral("{}").err(),
+            Some("Missing field tcb_info_issuer_chain".to_string())
+        );
+    }
+
+    #[test]
+    fn test_report_data_for_public_key() {
+        let public_key: PublicKey = "ed25519:DcA2MzgpJbrUATQLLceocVckhhAqrkingax4oJ9kZ847"
+            .parse()
+            .unwrap();
+        let report_data = report_data_for_public_key(&public_key);
+
+        assert_eq!(report_data.len(), 128);
+        assert!(report_data.ends_with(&encode(String::from(&public_key))));
+    }
+}
@@ -0,0 +1,416 @@
+//! Build the `register_worker` arguments from the output of dstack, after checking them the way
+//! the contract will.
+//!
+//! ```text
+//! tee-rng-register-args --quote <quote> --tcb-info <tcb_info> --collateral <collateral>
+//!     --public-key <public_key> [--checksum <checksum>] [--approved-codehash <codehash>]...
+//!     [--timestamp <seconds>]
+//! ```
+//!
+//! - `--quote`: the response of tappd's `TdxQuote`, or the quote hex
+//! - `--tcb-info`: the response of tappd's `Info`, or its `tcb_info`
+//! - `--collateral`: the response of the proof explorer upload, or the quote collateral alone
+//!   with `--checksum`
+//! - `--public-key`: the worker key signing `register_worker`, the quote report data has to
+//!   commit to it
+//!
+//! The checks are printed to stderr. If they pass, the arguments are printed to stdout as JSON.
+
+use std::process::ExitCode;
+use std::time::SystemTime;
+use std::{env, fs};
+
+use near_sdk::serde_json::{self, Value};
+use near_sdk::PublicKey;
+use tee_rng_sdk::RegisterWorkerArgs;
+use tee_rng_verifier::verify_registration;
+
+const USAGE: &str = "Usage: tee-rng-register-args --quote <quote> --tcb-info <tcb_info> --collateral <collateral> --public-key <public_key> [--checksum <checksum>] [--approved-codehash <codehash>]... [--timestamp <seconds>]";
+
+struct Args {
+    register_args: RegisterWorkerArgs,
+    public_key: PublicKey,
+    approved_codehashes: Vec<String>,
+    timestamp: Option<u64>,
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let now = args.timestamp.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("Failed to get current time")
+            .as_secs()
+    });
+    let approved_codehashes =
+        (!args.approved_codehashes.is_empty()).then_some(args.approved_codehashes.as_slice());
+
+    let verification = verify_registration(
+        &args.register_args.quote_hex,
+        &args.register_args.collateral,
+        &args.register_args.tcb_info,
+        &args.public_key,
+        now,
+        approved_codehashes,
+    );
+    eprint!("{}", verification);
+    if !verification.is_valid() {
+        return ExitCode::FAILURE;
+    }
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&args.register_args).unwrap()
+    );
+    ExitCode::SUCCESS
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut quote_hex = None;
+    let mut tcb_info = None;
+    let mut collateral = None;
+    let mut checksum = None;
+    let mut public_key = None;
+    let mut approved_codehashes = Vec::new();
+    let mut timestamp = None;
+
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {}", flag))?;
+        match flag.as_str() {
+            "--quote" => quote_hex = Some(read_quote(&value)?),
+            "--tcb-info" => tcb_info = Some(read_tcb_info(&value)?),
+            "--collateral" => {
+                let (quote_collateral, upload_checksum) = read_collateral(&value)?;
+                collateral = Some(quote_collateral);
+                checksum = checksum.or(upload_checksum);
+            }
+            "--checksum" => checksum = Some(value),
+            "--public-key" => {
+                public_key = Some(
+                    value
+                        .parse()
+                        .map_err(|err| format!("Invalid public key {}: {}", value, err))?,
+                );
+            }
+            "--approved-codehash" => approved_codehashes.push(value),
+            "--timestamp" => {
+                timestamp = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("Invalid timestamp {}", value))?,
+                );
+            }
+            _ => return Err(format!("Unknown argument {}", flag)),
+        }
+    }
+
+    Ok(Args {
+        register_args: RegisterWorkerArgs {
+            quote_hex: quote_hex.ok_or("Missing --quote")?,
+            collateral: collateral.ok_or("Missing --collateral")?,
+            checksum: checksum.ok_or("Missing --checksum")?,
+            tcb_info: tcb_info.ok_or("Missing --tcb-info")?,
+        },
+        public_key: public_key.ok_or("Missing --public-key")?,
+        approved_codehashes,
+        timestamp,
+    })
+}
+
+fn read_file(path: &str) -> Result<String, String> {
+    fs::read_to_string(path)
+        .map(|content| content.trim().to_string())
+        .map_err(|err| format!("Failed to read {}: {}", path, err))
+}
+
+fn read_json(path: &str) -> Result<Option<Value>, String> {
+    let content = read_file(path)?;
+    if !content.starts_with('{') {
+        return Ok(None);
+    }
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|err| format!("Invalid JSON in {}: {}", path, err))
+}
+
+/// The quote hex from a `TdxQuote` response or a hex file, without the `0x` prefix
+fn read_quote(path: &str) -> Result<String, String> {
+    let quote = match read_json(path)? {
+        Some(response) => response["quote"]
+            .as_str()
+            .ok_or_else(|| format!("Missing quote in {}", path))?
+            .to_string(),
+        None => read_file(path)?,
+    };
+    Ok(quote.trim_start_matches("0x").to_string())
+}
+
+/// The TCB info from an `Info` response, where it's either a JSON string or an object
+fn read_tcb_info(path: &str) -> Result<String, String> {
+    let info = read_json(path)?.ok_or_else(|| format!("{} should be JSON", path))?;
+    Ok(match &info["tcb_info"] {
+        Value::String(tcb_info) => tcb_info.clone(),
+        Value::Object(_) => info["tcb_info"].to_string(),
+        _ => info.to_string(),
+    })
+}
+
+/// The quote collateral, with the checksum if it's a proof explorer upload response
+fn read_collateral(path: &str) -> Result<(String, Option<String>), String> {
+    let collateral = read_json(path)?.ok_or_else(|| format!("{} should be JSON", path))?;
+    if collateral["quote_collateral"].is_object() {
+        let checksum = collateral["checksum"].as_str().map(str::to_string);
+        Ok((collateral["quote_collateral"].to_string(), checksum))
+    } else {
+        Ok((collateral.to_string(), None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::serde_json::json;
+    use std::path::PathBuf;
+    use std::process;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // The payloads below are hand-built and minimal. They're only read and passed through here,
+    // so they don't need to be valid attestations.
+
+    /// Header of a TDX quote: version 4, ECDSA-256 attestation key and TEE type TDX
+    const QUOTE_HEX: &str = "0400020081000000";
+    const CHECKSUM: &str = "upload-checksum";
+    const PUBLIC_KEY: &str = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp";
+
+    /// Response of tappd's `TdxQuote`
+    fn tdx_quote_response() -> Value {
+        json!({ "quote": format!("0x{}", QUOTE_HEX), "event_log": "[]" })
+    }
+
+    fn tcb_info() -> Value {
+        json!({ "rtmr3": "00".repeat(48), "event_log": [] })
+    }
+
+    /// Quote collateral as returned by the proof explorer
+    fn quote_collateral() -> Value {
+        json!({ "tcb_info": "{}", "qe_identity": "{}" })
+    }
+
+    /// Response of the proof explorer upload
+    fn upload_response() -> Value {
+        json!({ "checksum": CHECKSUM, "quote_collateral": quote_collateral() })
+    }
+
+    /// Sample payload in a temporary file, which is removed when dropped
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, content: &str) -> Self {
+            // Tests run in parallel, so every file gets its own name
+            static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+            let path = env::temp_dir().join(format!(
+                "tee-rng-register-args-{}-{}-{}",
+                process::id(),
+                NEXT_ID.fetch_add(1, Ordering::Relaxed),
+                name
+            ));
+            fs::write(&path, content).unwrap();
+            Self(path)
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn args(values: &[&str]) -> impl Iterator<Item = String> {
+        values
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_read_quote() {
+        let response = TempFile::new("quote.json", &tdx_quote_response().to_string());
+        assert_eq!(read_quote(response.path()).unwrap(), QUOTE_HEX);
+
+        let hex = TempFile::new("quote.hex", &format!("0x{}\n", QUOTE_HEX));
+        assert_eq!(read_quote(hex.path()).unwrap(), QUOTE_HEX);
+
+        let hex = TempFile::new("quote.hex", QUOTE_HEX);
+        assert_eq!(read_quote(hex.path()).unwrap(), QUOTE_HEX);
+    }
+
+    #[test]
+    fn test_read_quote_without_quote() {
+        let file = TempFile::new("quote.json", r#"{"event_log":"[]"}"#);
+        assert!(read_quote(file.path())
+            .unwrap_err()
+            .starts_with("Missing quote in"));
+    }
+
+    #[test]
+    fn test_read_tcb_info() {
+        // tappd returns the TCB info as a JSON string
+        let info = TempFile::new(
+            "info.json",
+            &json!({ "tcb_info": tcb_info().to_string() }).to_string(),
+        );
+        assert_eq!(read_tcb_info(info.path()).unwrap(), tcb_info().to_string());
+
+        let info = TempFile::new("info.json", &json!({ "tcb_info": tcb_info() }).to_string());
+        assert_eq!(read_tcb_info(info.path()).unwrap(), tcb_info().to_string());
+
+        // The TCB info alone
+        let info = TempFile::new("tcb-info.json", &tcb_info().to_string());
+        assert_eq!(read_tcb_info(info.path()).unwrap(), tcb_info().to_string());
+    }
+
+    #[test]
+    fn test_read_tcb_info_not_json() {
+        let file = TempFile::new("tcb-info.txt", "rtmr3");
+        assert!(read_tcb_info(file.path())
+            .unwrap_err()
+            .ends_with("should be JSON"));
+
+        let file = TempFile::new("tcb-info.json", "{\"rtmr3\":");
+        assert!(read_tcb_info(file.path())
+            .unwrap_err()
+            .starts_with("Invalid JSON in"));
+    }
+
+    #[test]
+    fn test_read_collateral() {
+        let upload = TempFile::new("upload.json", &upload_response().to_string());
+        assert_eq!(
+            read_collateral(upload.path()).unwrap(),
+            (quote_collateral().to_string(), Some(CHECKSUM.to_string()))
+        );
+
+        let collateral = TempFile::new("collateral.json", &quote_collateral().to_string());
+        assert_eq!(
+            read_collateral(collateral.path()).unwrap(),
+            (quote_collateral().to_string(), None)
+        );
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let quote = TempFile::new("quote.json", &tdx_quote_response().to_string());
+        let info = TempFile::new(
+            "info.json",
+            &json!({ "tcb_info": tcb_info().to_string() }).to_string(),
+        );
+        let upload = TempFile::new("upload.json", &upload_response().to_string());
+        let codehash = "a".repeat(64);
+
+        let parsed = parse_args(args(&[
+            "--quote",
+            quote.path(),
+            "--tcb-info",
+            info.path(),
+            "--collateral",
+            upload.path(),
+            "--public-key",
+            PUBLIC_KEY,
+            "--approved-codehash",
+            &codehash,
+            "--timestamp",
+            "1700000000",
+        ]))
+        .unwrap();
+
+        assert_eq!(parsed.register_args.quote_hex, QUOTE_HEX);
+        assert_eq!(parsed.register_args.tcb_info, tcb_info().to_string());
+        assert_eq!(
+            parsed.register_args.collateral,
+            quote_collateral().to_string()
+        );
+        assert_eq!(parsed.register_args.checksum, CHECKSUM);
+        assert_eq!(parsed.public_key, PUBLIC_KEY.parse().unwrap());
+        assert_eq!(parsed.approved_codehashes, vec![codehash]);
+        assert_eq!(parsed.timestamp, Some(1_700_000_000));
+    }
+
+    #[test]
+    fn test_parse_args_checksum() {
+        let quote = TempFile::new("quote.hex", QUOTE_HEX);
+        let info = TempFile::new("info.json", &tcb_info().to_string());
+        let upload = TempFile::new("upload.json", &upload_response().to_string());
+        let collateral = TempFile::new("collateral.json", &quote_collateral().to_string());
+
+        // --checksum takes precedence over the upload response, in any order
+        for collateral_args in [
+            ["--collateral", upload.path(), "--checksum", "explicit"],
+            ["--checksum", "explicit", "--collateral", upload.path()],
+        ] {
+            let mut values: Vec<&str> = vec![
+                "--quote",
+                quote.path(),
+                "--tcb-info",
+                info.path(),
+                "--public-key",
+                PUBLIC_KEY,
+            ];
+            values.extend(collateral_args);
+            let parsed = parse_args(args(&values)).unwrap();
+            assert_eq!(parsed.register_args.checksum, "explicit");
+        }
+
+        // The collateral alone has no checksum
+        let result = parse_args(args(&[
+            "--quote",
+            quote.path(),
+            "--tcb-info",
+            info.path(),
+            "--collateral",
+            collateral.path(),
+            "--public-key",
+            PUBLIC_KEY,
+        ]));
+        assert_eq!(result.err().unwrap(), "Missing --checksum");
+    }
+
+    #[test]
+    fn test_parse_invalid_args() {
+        assert_eq!(
+            parse_args(args(&["--quote"])).err().unwrap(),
+            "Missing value for --quote"
+        );
+        assert_eq!(
+            parse_args(args(&["--verbose", "true"])).err().unwrap(),
+            "Unknown argument --verbose"
+        );
+        assert!(parse_args(args(&["--public-key", "alice.near"]))
+            .err()
+            .unwrap()
+            .starts_with("Invalid public key alice.near"));
+        assert_eq!(
+            parse_args(args(&["--timestamp", "now"])).err().unwrap(),
+            "Invalid timestamp now"
+        );
+        assert_eq!(
+            parse_args(args(&["--public-key", PUBLIC_KEY]))
+                .err()
+                .unwrap(),
+            "Missing --quote"
+        );
+    }
+}
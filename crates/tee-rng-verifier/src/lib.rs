@@ -0,0 +1,21 @@
+//! Verification of worker attestations and responses of the TEE RNG contract. The contract runs
+//! the attestation checks and the response message hash from this crate, so verifying offline
+//! gives the same results as on-chain.
+//!
+//! [`verify_registration`] reports the outcome of every check of `register_worker` instead of
+//! panicking. With the default `offline` feature, signatures are verified without the NEAR host
+//! functions and [`verify_response`] reruns every check of a past response for auditing.
+
+pub use dcap_qvl;
+
+pub use crate::attestation::*;
+pub use crate::registration::*;
+#[cfg(feature = "offline")]
+pub use crate::report::*;
+pub use crate::signature::*;
+
+mod attestation;
+mod registration;
+#[cfg(feature = "offline")]
+mod report;
+mod signature;
@@ -0,0 +1,198 @@
+//! Verify a past response of the TEE RNG contract without trusting an RPC node.
+//!
+//! ```text
+//! tee-rng-verifier --request <event> --response <event> --worker <registration.json>
+//!     [--approved-codehash <codehash>]... [--timestamp <seconds>]
+//! ```
+//!
+//! Events are the `EVENT_JSON:` log lines of the `request` and `response` events, or files
+//! containing them. The registration file holds the `register_worker` arguments of the worker
+//! with its `worker_id`, `public_key` and optionally `registered_at` in seconds.
+
+use std::process::ExitCode;
+use std::time::SystemTime;
+use std::{env, fs};
+
+use near_sdk::serde_json;
+use tee_rng_events::{parse_event_log, Event};
+use tee_rng_verifier::{verify_response, WorkerRegistration};
+
+const USAGE: &str = "Usage: tee-rng-verifier --request <event> --response <event> --worker <registration.json> [--approved-codehash <codehash>]... [--timestamp <seconds>]";
+
+struct Args {
+    request: Event,
+    response: Event,
+    registration: WorkerRegistration,
+    approved_codehashes: Vec<String>,
+    timestamp: Option<u64>,
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    // Collateral expires, so past registrations are verified at the time they were made
+    let now = args
+        .timestamp
+        .or(args.registration.registered_at)
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .expect("Failed to get current time")
+                .as_secs()
+        });
+    let approved_codehashes =
+        (!args.approved_codehashes.is_empty()).then_some(args.approved_codehashes.as_slice());
+
+    match verify_response(
+        &args.request,
+        &args.response,
+        &args.registration,
+        now,
+        approved_codehashes,
+    ) {
+        Ok(report) => {
+            println!("{}", report);
+            if report.is_valid() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut request = None;
+    let mut response = None;
+    let mut registration = None;
+    let mut approved_codehashes = Vec::new();
+    let mut timestamp = None;
+    let mut seen_flags = Vec::new();
+
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {}", flag))?;
+        // Only approved codehashes can be given more than once
+        if flag != "--approved-codehash" && seen_flags.contains(&flag) {
+            return Err(format!("Duplicate argument {}", flag));
+        }
+        seen_flags.push(flag.clone());
+        match flag.as_str() {
+            "--request" => request = Some(read_event(&value)?),
+            "--response" => response = Some(read_event(&value)?),
+            "--worker" => {
+                let registration_json = fs::read_to_string(&value)
+                    .map_err(|err| format!("Failed to read {}: {}", value, err))?;
+                registration = Some(
+                    serde_json::from_str(&registration_json)
+                        .map_err(|err| format!("Invalid worker registration: {}", err))?,
+                );
+            }
+            "--approved-codehash" => approved_codehashes.push(value),
+            "--timestamp" => {
+                timestamp = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("Invalid timestamp {}", value))?,
+                );
+            }
+            _ => return Err(format!("Unknown argument {}", flag)),
+        }
+    }
+
+    Ok(Args {
+        request: request.ok_or("Missing --request")?,
+        response: response.ok_or("Missing --response")?,
+        registration: registration.ok_or("Missing --worker")?,
+        approved_codehashes,
+        timestamp,
+    })
+}
+
+/// Parse an event log line, given directly or in a file
+fn read_event(value: &str) -> Result<Event, String> {
+    let log = match fs::read_to_string(value) {
+        Ok(content) => content,
+        Err(_) => value.to_string(),
+    };
+    parse_event_log(log.trim())
+        .map(|event_log| event_log.event)
+        .map_err(|err| format!("Invalid event {}: {}", value, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST_LOG: &str = r#"EVENT_JSON:{"data":[{"account_id":"alice.near","block_height":100,"payment":{"Near":"5000000000000000000000"},"random_seed":[1,2,3],"request_id":1}],"event":"request","standard":"tee-rng","version":"1.1.0"}"#;
+    const RESPONSE_LOG: &str = r#"EVENT_JSON:{"data":[{"random_number":[4,5,6],"request_id":1,"worker_id":"worker.near"}],"event":"response","standard":"tee-rng","version":"1.1.0"}"#;
+
+    fn parse_error(args: &[&str]) -> String {
+        match parse_args(args.iter().map(|arg| arg.to_string())) {
+            Ok(_) => panic!("Expected {:?} to be rejected", args),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn test_parse_missing_flags() {
+        assert_eq!(parse_error(&[]), "Missing --request");
+        assert_eq!(
+            parse_error(&["--request", REQUEST_LOG]),
+            "Missing --response"
+        );
+        assert_eq!(
+            parse_error(&["--request", REQUEST_LOG, "--response", RESPONSE_LOG]),
+            "Missing --worker"
+        );
+    }
+
+    #[test]
+    fn test_parse_missing_value() {
+        assert_eq!(
+            parse_error(&["--request", REQUEST_LOG, "--timestamp"]),
+            "Missing value for --timestamp"
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_flag() {
+        assert_eq!(
+            parse_error(&["--request", REQUEST_LOG, "--verbose", "true"]),
+            "Unknown argument --verbose"
+        );
+    }
+
+    #[test]
+    fn test_parse_duplicated_flag() {
+        assert_eq!(
+            parse_error(&["--timestamp", "1", "--timestamp", "2"]),
+            "Duplicate argument --timestamp"
+        );
+        assert_eq!(
+            parse_error(&["--request", REQUEST_LOG, "--request", REQUEST_LOG]),
+            "Duplicate argument --request"
+        );
+        // Approved codehashes can be repeated
+        assert_eq!(
+            parse_error(&[
+                "--approved-codehash",
+                &"a".repeat(64),
+                "--approved-codehash",
+                &"b".repeat(64),
+            ]),
+            "Missing --request"
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt;
+
+use hex::encode;
+use near_sdk::PublicKey;
+use tee_rng_sdk::RegistrationReport;
+
+use crate::attestation::{
+    get_collateral, replay_tcb_info_rtmr3, report_data_for_public_key, td_report, verify_codehash,
+    verify_quote,
+};
+
+/// Outcome of the checks `register_worker` runs on a quote. Each check holds the error message
+/// the contract would have panicked with.
+#[derive(Clone, Debug)]
+pub struct RegistrationVerification {
+    /// TCB status of the quote verified with `dcap_qvl::verify`
+    pub quote: Result<String, String>,
+    /// The report data of the quote commits to the worker public key
+    pub report_data: Result<(), String>,
+    /// The event log of the TCB info replays to the rtmr3 of the quote
+    pub rtmr3: Result<(), String>,
+    /// Codehash extracted from the TCB info replayed against rtmr3
+    pub codehash: Result<String, String>,
+    /// Whether the codehash is one of the given approved codehashes
+    pub codehash_approved: Option<bool>,
+}
+
+impl RegistrationVerification {
+    pub fn is_valid(&self) -> bool {
+        self.quote.is_ok()
+            && self.report_data.is_ok()
+            && self.rtmr3.is_ok()
+            && self.codehash.is_ok()
+            && self.codehash_approved != Some(false)
+    }
+}
+
+/// Rerun the checks of `register_worker` for a quote of `public_key`. The quote is verified at
+/// `now` (seconds since the Unix epoch). The codehash approval is only checked if approved
+/// codehashes are given.
+pub fn verify_registration(
+    quote_hex: &str,
+    collateral: &str,
+    tcb_info: &str,
+    public_key: &PublicKey,
+    now: u64,
+    approved_codehashes: Option<&[String]>,
+) -> RegistrationVerification {
+    let quote =
+        get_collateral(collateral).and_then(|collateral| verify_quote(quote_hex, &collateral, now));
+    let report = match &quote {
+        Ok(result) => td_report(result),
+        Err(_) => Err("Skipped, the quote is not verified".to_string()),
+    };
+
+    let report_data = report.as_ref().map_err(Clone::clone).and_then(|report| {
+        let report_data = encode(report.report_data);
+        let public_key_hex = report_data_for_public_key(public_key);
+        if public_key_hex == report_data {
+            Ok(())
+        } else {
+            Err(format!(
+                "Invalid public key: {} v.s. {}",
+                public_key_hex, report_data
+            ))
+        }
+    });
+
+    let rtmr3 = report.as_ref().map_err(Clone::clone).and_then(|report| {
+        if replay_tcb_info_rtmr3(tcb_info)? == encode(report.rt_mr3) {
+            Ok(())
+        } else {
+            Err("Invalid rtmr3".to_string())
+        }
+    });
+
+    let codehash = report
+        .as_ref()
+        .map_err(Clone::clone)
+        .and_then(|report| verify_codehash(tcb_info, &encode(report.rt_mr3)));
+    let codehash_approved = approved_codehashes.map(|approved_codehashes| {
+        codehash
+            .as_ref()
+            .is_ok_and(|codehash| approved_codehashes.contains(codehash))
+    });
+
+    RegistrationVerification {
+        quote: quote.map(|result| result.status),
+        report_data,
+        rtmr3,
+        codehash,
+        codehash_approved,
+    }
+}
+
+impl From<RegistrationVerification> for RegistrationReport {
+    fn from(verification: RegistrationVerification) -> Self {
+        let mut errors: Vec<String> = [
+            verification.quote.as_ref().err(),
+            verification.report_data.as_ref().err(),
+            verification.rtmr3.as_ref().err(),
+            verification.codehash.as_ref().err(),
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+        // The checks depending on the quote are skipped with the same error
+        errors.dedup();
+        if verification.codehash.is_ok() && verification.codehash_approved == Some(false) {
+            errors.push("Invalid code hash".to_string());
+        }
+
+        RegistrationReport {
+            quote_verified: verification.quote.is_ok(),
+            tcb_status: verification.quote.ok(),
+            report_data_matches: verification.report_data.is_ok(),
+            rtmr3_matches: verification.rtmr3.is_ok(),
+            codehash: verification.codehash.ok(),
+            codehash_approved: verification.codehash_approved == Some(true),
+            errors,
+        }
+    }
+}
+
+impl fmt::Display for RegistrationVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_check(f, "Quote", &self.quote, |status| {
+            format!("TCB status {}", status)
+        })?;
+        write_check(f, "Report data", &self.report_data, |_| {
+            "matches the worker public key".to_string()
+        })?;
+        write_check(f, "RTMR3", &self.rtmr3, |_| {
+            "matches the TCB info event log".to_string()
+        })?;
+        write_check(f, "Codehash", &self.codehash, |codehash| {
+            match self.codehash_approved {
+                Some(true) => format!("{} (approved)", codehash),
+                Some(false) => format!("{} (NOT approved)", codehash),
+                None => codehash.clone(),
+            }
+        })
+    }
+}
+
+/// Write a line of a report with the details of a passed check, or why it failed
+pub(crate) fn write_check<T>(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    result: &Result<T, String>,
+    details: impl Fn(&T) -> String,
+) -> fmt::Result {
+    let name = format!("{}:", name);
+    match result {
+        Ok(value) => writeln!(f, "{:<13}OK, {}", name, details(value)),
+        Err(err) => writeln!(f, "{:<13}FAILED, {}", name, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_registration_with_invalid_quote() {
+        let public_key: PublicKey = "ed25519:DcA2MzgpJbrUATQLLceocVckhhAqrkingax4oJ9kZ847"
+            .parse()
+            .unwrap();
+        let verification = verify_registration(
+            "00",
+            "{}",
+            "{}",
+            &public_key,
+            0,
+            Some(&["abcd".to_string()][..]),
+        );
+
+        assert_eq!(
+            verification.quote,
+            Err("Missing field tcb_info_issuer_chain".to_string())
+        );
+        assert_eq!(
+            verification.codehash,
+            Err("Skipped, the quote is not verified".to_string())
+        );
+        assert_eq!(verification.codehash_approved, Some(false));
+        assert!(!verification.is_valid());
+        assert!(verification
+            .to_string()
+            .starts_with("Quote:       FAILED, Missing field tcb_info_issuer_chain"));
+
+        let report = RegistrationReport::from(verification);
+        assert!(!report.quote_verified);
+        assert!(!report.rtmr3_matches);
+        assert_eq!(report.codehash, None);
+        assert_eq!(
+            report.errors,
+            vec![
+                "Missing field tcb_info_issuer_chain".to_string(),
+                "Skipped, the quote is not verified".to_string()
+            ]
+        );
+        assert!(!report.is_valid());
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt;
+
+use near_sdk::serde::Deserialize;
+use near_sdk::{AccountId, PublicKey};
+use tee_rng_events::Event;
+use tee_rng_sdk::RegisterWorkerArgs;
+
+use crate::registration::{verify_registration, write_check, RegistrationVerification};
+use crate::signature::{ed25519_public_key_bytes, verify_response_signature};
+
+/// The arguments a worker passed to `register_worker`, with the public key it signed the
+/// transaction with
+#[derive(Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WorkerRegistration {
+    pub worker_id: AccountId,
+    pub public_key: PublicKey,
+    #[serde(flatten)]
+    pub args: RegisterWorkerArgs,
+    /// Registration time in seconds, the collateral is checked against it
+    #[serde(default)]
+    pub registered_at: Option<u64>,
+}
+
+/// Outcome of every check the contract runs on the worker registration and the response.
+/// Each check holds the error message the contract would have panicked with.
+#[derive(Clone, Debug)]
+pub struct VerificationReport {
+    pub request_id: u64,
+    pub worker_id: AccountId,
+    /// Checks of `register_worker` on the worker registration
+    pub registration: RegistrationVerification,
+    /// The response is signed by the worker public key
+    pub signature: Result<(), String>,
+}
+
+impl VerificationReport {
+    pub fn is_valid(&self) -> bool {
+        self.registration.is_valid() && self.signature.is_ok()
+    }
+}
+
+/// Rerun the checks of `register_worker` and `respond` for a response. The quote is verified at
+/// `now` (seconds since the Unix epoch), which should be the registration time for past
+/// registrations since collateral expires. Fails if the events aren't a request and its response
+/// by the registered worker.
+pub fn verify_response(
+    request: &Event,
+    response: &Event,
+    registration: &WorkerRegistration,
+    now: u64,
+    approved_codehashes: Option<&[String]>,
+) -> Result<VerificationReport, String> {
+    let Event::Request {
+        request_id,
+        random_seed,
+        ..
+    } = request
+    else {
+        return Err("Expected a request event".to_string());
+    };
+    let Event::Response {
+        worker_id,
+        request_id: response_request_id,
+        random_number,
+        signature,
+    } = response
+    else {
+        return Err("Expected a response event".to_string());
+    };
+    if request_id != response_request_id {
+        return Err(format!(
+            "The response is for request {}, not {}",
+            response_request_id, request_id
+        ));
+    }
+    if *worker_id != registration.worker_id {
+        return Err(format!(
+            "The response is from {}, not {}",
+            worker_id, registration.worker_id
+        ));
+    }
+
+    let registration_verification = verify_registration(
+        &registration.args.quote_hex,
+        &registration.args.collateral,
+        &registration.args.tcb_info,
+        &registration.public_key,
+        now,
+        approved_codehashes,
+    );
+
+    let signature = ed25519_public_key_bytes(&registration.public_key).and_then(|public_key| {
+        if verify_response_signature(
+            &public_key,
+            *request_id,
+            random_seed,
+            random_number,
+            signature,
+        ) {
+            Ok(())
+        } else {
+            Err("Invalid signature".to_string())
+        }
+    });
+
+    Ok(VerificationReport {
+        request_id: *request_id,
+        worker_id: worker_id.clone(),
+        registration: registration_verification,
+        signature,
+    })
+}
+
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Request {} answered by {}",
+            self.request_id, self.worker_id
+        )?;
+        write!(f, "{}", self.registration)?;
+        write_check(f, "Signature", &self.signature, |_| {
+            "signed by the worker public key".to_string()
+        })?;
+        let result = if self.is_valid() { "VALID" } else { "INVALID" };
+        write!(f, "{:<13}{}", "Result:", result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::response_message_hash;
+    use ed25519_dalek::{Signer, SigningKey};
+    use near_sdk::json_types::U128;
+    use near_sdk::CurveType;
+    use rand::rngs::OsRng;
+    use tee_rng_events::Payment;
+
+    fn request_event() -> Event {
+        Event::Request {
+            account_id: "alice.near".parse().unwrap(),
+            request_id: 1,
+            random_seed: vec![1; 32],
+            payment: Payment::FungibleToken {
+                token_id: "usdt.near".parse().unwrap(),
+                amount: U128(1),
+            },
+            block_height: 100,
+        }
+    }
+
+    fn registration(signing_key: &SigningKey) -> WorkerRegistration {
+        let key_bytes = signing_key.verifying_key().to_bytes().to_vec();
+        WorkerRegistration {
+            worker_id: "worker.near".parse().unwrap(),
+            public_key: PublicKey::from_parts(CurveType::ED25519, key_bytes).unwrap(),
+            args: RegisterWorkerArgs {
+                quote_hex: "00".to_string(),
+                collateral: "{}".to_string(),
+                checksum: "checksum".to_string(),
+                tcb_info: "{}".to_string(),
+            },
+            registered_at: None,
+        }
+    }
+
+    fn response_event(signing_key: &SigningKey, request_id: u64) -> Event {
+        let random_number = vec![2; 32];
+        let message_hash = response_message_hash(request_id, &[1; 32], &random_number);
+        Event::Response {
+            worker_id: "worker.near".parse().unwrap(),
+            request_id,
+            random_number,
+            signature: signing_key.sign(&message_hash).to_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_verify_response() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let report = verify_response(
+            &request_event(),
+            &response_event(&signing_key, 1),
+            &registration(&signing_key),
+            0,
+            Some(&[][..]),
+        )
+        .unwrap();
+
+        assert_eq!(report.signature, Ok(()));
+        // The collateral is invalid, so the attestation checks fail
+        assert!(report.registration.quote.is_err());
+        assert!(report.registration.report_data.is_err());
+        assert!(report.registration.codehash.is_err());
+        assert_eq!(report.registration.codehash_approved, Some(false));
+        assert!(!report.is_valid());
+        assert!(report.to_string().ends_with("Result:      INVALID"));
+    }
+
+    #[test]
+    fn test_verify_response_with_another_signer() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let other_signing_key = SigningKey::generate(&mut OsRng);
+        let report = verify_response(
+            &request_event(),
+            &response_event(&other_signing_key, 1),
+            &registration(&signing_key),
+            0,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(report.signature, Err("Invalid signature".to_string()));
+    }
+
+    #[test]
+    fn test_verify_response_for_another_request() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let result = verify_response(
+            &request_event(),
+            &response_event(&signing_key, 2),
+            &registration(&signing_key),
+            0,
+            None,
+        );
+
+        assert_eq!(
+            result.err(),
+            Some("The response is for request 2, not 1".to_string())
+        );
+    }
+}
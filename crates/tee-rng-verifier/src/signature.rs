@@ -0,0 +1,111 @@
+use near_sdk::PublicKey;
+use sha3::{Digest, Keccak256};
+
+/// Extract the raw 32-byte ED25519 key from a NEAR public key
+pub fn ed25519_public_key_bytes(public_key: &PublicKey) -> Result<[u8; 32], String> {
+    let public_key_bytes_slice = public_key.as_bytes();
+
+    // NEAR public keys include a curve type prefix (1 byte) + actual key (32 bytes)
+    // For ED25519, the first byte should be 0
+    if public_key_bytes_slice.len() == 33 && public_key_bytes_slice[0] == 0 {
+        // Extract the actual 32-byte public key (skip the first byte)
+        Ok(public_key_bytes_slice[1..33].try_into().unwrap())
+    } else if public_key_bytes_slice.len() == 32 {
+        // Already 32 bytes, use as is
+        Ok(public_key_bytes_slice.try_into().unwrap())
+    } else {
+        // Unexpected format
+        Err(format!(
+            "Unexpected public key format: {} bytes, first byte: {}",
+            public_key_bytes_slice.len(),
+            public_key_bytes_slice.first().unwrap_or(&255)
+        ))
+    }
+}
+
+/// The message hash signed by workers: keccak256(keccak256(requestId, seed, random))
+pub fn response_message_hash(request_id: u64, random_seed: &[u8], random_number: &[u8]) -> Vec<u8> {
+    let mut message_hasher = Keccak256::new();
+    message_hasher.update(request_id.to_le_bytes()); // requestId
+    message_hasher.update(random_seed); // seed
+    message_hasher.update(random_number); // random
+    let first_hash = message_hasher.finalize();
+
+    // Double hash: keccak256(keccak256(...))
+    let mut double_hasher = Keccak256::new();
+    double_hasher.update(first_hash);
+    double_hasher.finalize().to_vec()
+}
+
+/// Verify the response is signed by the given worker's public key, like the contract does with
+/// the `ed25519_verify` host function
+#[cfg(feature = "offline")]
+pub fn verify_response_signature(
+    public_key: &[u8; 32],
+    request_id: u64,
+    random_seed: &[u8],
+    random_number: &[u8],
+    signature: &[u8],
+) -> bool {
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    let Ok(signature) = Signature::from_slice(signature) else {
+        return false;
+    };
+    let Ok(verifying_key) = VerifyingKey::from_bytes(public_key) else {
+        return false;
+    };
+    let message_hash = response_message_hash(request_id, random_seed, random_number);
+    verifying_key.verify(&message_hash, &signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use rand::rngs::OsRng;
+
+    #[test]
+    #[cfg(feature = "offline")]
+    fn test_verify_response_signature() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let public_key = signing_key.verifying_key().to_bytes();
+        let random_seed = vec![1; 32];
+        let random_number = vec![2; 32];
+
+        let message_hash = response_message_hash(7, &random_seed, &random_number);
+        let signature = signing_key.sign(&message_hash).to_bytes();
+
+        assert!(verify_response_signature(
+            &public_key,
+            7,
+            &random_seed,
+            &random_number,
+            &signature
+        ));
+        assert!(!verify_response_signature(
+            &public_key,
+            8,
+            &random_seed,
+            &random_number,
+            &signature
+        ));
+        assert!(!verify_response_signature(
+            &public_key,
+            7,
+            &random_seed,
+            &random_number,
+            &signature[..63]
+        ));
+    }
+
+    #[test]
+    fn test_ed25519_public_key_bytes() {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let key_bytes = signing_key.verifying_key().to_bytes();
+        let public_key =
+            PublicKey::from_parts(near_sdk::CurveType::ED25519, key_bytes.to_vec()).unwrap();
+
+        assert_eq!(ed25519_public_key_bytes(&public_key), Ok(key_bytes));
+    }
+}
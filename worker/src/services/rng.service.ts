@@ -8,6 +8,7 @@ import { createHash } from 'crypto';
 // Types matching the Rust contract structures
 export interface Request {
   request_id: number;
+  account_id: string;
   random_seed: Uint8Array;
   yield_index: {
     data_id: string;
@@ -24,6 +25,9 @@ export class RngService {
   private logger = new LoggerService('rng');
   private isRunning = false;
   private pollingInterval = 500; // 0.5 second
+  private lastRequestId = 0;
+  private maxAttempts = 3; // Attempts before a failing request is skipped
+  private failedAttempts = new Map<number, number>();
   private tappdClient: TappdClient;
 
   constructor(
@@ -81,8 +85,24 @@ export class RngService {
       for (const request of pendingRequests) {
         try {
           await this.processRequest(request);
+          this.failedAttempts.delete(request.request_id);
+          this.lastRequestId = request.request_id;
         } catch (error) {
           this.logger.error(`Error processing request ${request.request_id}: ${error}`);
+          const attempts = (this.failedAttempts.get(request.request_id) ?? 0) + 1;
+          if (attempts < this.maxAttempts) {
+            // Retry from this request on the next poll
+            this.failedAttempts.set(request.request_id, attempts);
+            break;
+          }
+
+          // Move past the request, so it doesn't block the following ones. It's left to
+          // time out and be refunded by the contract.
+          this.logger.error(
+            `Skipping request ${request.request_id} after ${attempts} failed attempts`
+          );
+          this.failedAttempts.delete(request.request_id);
+          this.lastRequestId = request.request_id;
         }
       }
     } catch (error) {
@@ -91,14 +111,14 @@ export class RngService {
   }
 
   /**
-   * Get pending requests from the contract
+   * Get pending requests after the last processed request from the contract
    */
   private async getPendingRequests(): Promise<Request[]> {
     return this.nearService.getSigner().viewFunction({
       contractId: this.contractId,
-      methodName: 'get_pending_requests',
+      methodName: 'get_pending_requests_since',
       args: {
-        offset: 0,
+        request_id: this.lastRequestId,
         limit: 10,
       },
     });